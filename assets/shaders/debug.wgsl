@@ -0,0 +1,104 @@
+#define_import_path ray_marching::debug
+
+#import ray_marching::ray::{GetDistanceInput, RayMarchOutput, get_distance, max_steps, shapes};
+#import ray_marching::lighting::{ShaderLight, get_shadow, light_direction};
+
+const pi: f32 = 3.14159265;
+
+struct ShaderDebug {
+    // 0 renders normally, see DebugView for the others
+    view: u32,
+    // The height of the horizontal plane the distance slice view shows
+    slice_height: f32,
+    // The distance that is black in the depth view
+    depth_range: f32,
+};
+
+// A false colour image of part of the ray march, shown instead of the shaded scene
+fn debug_colour(
+    debug: ShaderDebug,
+    ray_origin: vec3<f32>,
+    ray_dir: vec3<f32>,
+    ray_march_out: RayMarchOutput,
+    normal: vec3<f32>,
+    light: ShaderLight,
+    get_dist_input: GetDistanceInput,
+) -> vec3<f32> {
+    let p = ray_origin + ray_dir * ray_march_out.dist;
+    let hit = ray_march_out.hit;
+
+    switch debug.view {
+        // Steps, from blue for few to red for the most allowed
+        case (1u) {
+            return heatmap(f32(ray_march_out.steps) / f32(max_steps));
+        }
+        // Normals
+        case (2u) {
+            return select(vec3<f32>(0.), normal * 0.5 + 0.5, hit);
+        }
+        // Depth
+        case (3u) {
+            return select(vec3<f32>(0.), vec3<f32>(1. - clamp(ray_march_out.dist / debug.depth_range, 0., 1.)), hit);
+        }
+        // Shape ID, a different colour for each shape which stays the same when the shapes are reordered
+        case (4u) {
+            return select(vec3<f32>(0.), index_colour(shapes[ray_march_out.shape_index].id), hit);
+        }
+        // Minimum distance, red where rays passed close to (or hit) a shape
+        case (5u) {
+            return heatmap(exp(-ray_march_out.min_dist * 4.));
+        }
+        // Shadow mask
+        case (6u) {
+            if !hit {
+                return vec3<f32>(0.);
+            }
+
+            return vec3<f32>(get_shadow(p, normal, light_direction(p, light), get_dist_input));
+        }
+        // Distance slice
+        case (7u) {
+            return distance_slice(debug.slice_height, ray_origin, ray_dir, ray_march_out, normal, get_dist_input);
+        }
+        default {
+            return vec3<f32>(0.);
+        }
+    }
+}
+
+// The distance field on a horizontal plane, orange inside shapes and blue outside, with a contour every quarter unit
+// and the surface in white. Shapes behind the plane are shown dimly by their normals
+fn distance_slice(
+    height: f32,
+    ray_origin: vec3<f32>,
+    ray_dir: vec3<f32>,
+    ray_march_out: RayMarchOutput,
+    normal: vec3<f32>,
+    get_dist_input: GetDistanceInput,
+) -> vec3<f32> {
+    let to_slice = (height - ray_origin.y) / ray_dir.y;
+
+    if abs(ray_dir.y) > 1e-5 && to_slice > 0. && (!ray_march_out.hit || to_slice < ray_march_out.dist) {
+        let dist = get_distance(ray_origin + ray_dir * to_slice, get_dist_input).dist;
+
+        var colour = select(vec3<f32>(0.9, 0.6, 0.3), vec3<f32>(0.65, 0.85, 1.), dist > 0.);
+        colour *= 1. - exp(-6. * abs(dist));
+        colour *= 0.8 + 0.2 * cos(dist * 8. * pi);
+
+        return mix(colour, vec3<f32>(1.), 1. - smoothstep(0., 0.02, abs(dist)));
+    }
+
+    return select(vec3<f32>(0.), (normal * 0.5 + 0.5) * 0.5, ray_march_out.hit);
+}
+
+// Blue to red through cyan, green and yellow
+fn heatmap(t: f32) -> vec3<f32> {
+    let x = clamp(t, 0., 1.);
+
+    return clamp(vec3<f32>(1.5 - abs(4. * x - 3.), 1.5 - abs(4. * x - 2.), 1.5 - abs(4. * x - 1.)), vec3<f32>(0.), vec3<f32>(1.));
+}
+
+// Step around the hue wheel by the golden ratio, so neighbouring indices have very different colours
+fn index_colour(index: u32) -> vec3<f32> {
+    return 0.5 + 0.5 * cos(2. * pi * (f32(index) * 0.618034 + vec3<f32>(0., 0.33, 0.67)));
+}
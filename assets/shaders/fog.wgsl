@@ -0,0 +1,215 @@
+#define_import_path ray_marching::fog
+
+#import ray_marching::lighting::{ShaderLight, light_direction};
+#import ray_marching::noise::perlin_fbm_3d;
+#import ray_marching::shapes::sdf_box;
+#import ray_marching::sky::{sky, sky_ambient, sky_fog_colour, ray_sphere};
+
+const pi: f32 = 3.14159265;
+const max_volumes = 4;
+const volume_light_steps = 4;
+
+@group(2) @binding(9)
+var<uniform> fog: ShaderFog;
+
+struct ShaderVolume {
+    // 0 for a sphere with a radius of size.x, 1 for a box with half extents of size
+    shape: u32,
+    pos: vec3<f32>,
+    size: vec3<f32>,
+    density: f32,
+    // How much of the light hitting the volume is scattered rather than absorbed
+    colour: vec3<f32>,
+    // 0 for an even density, otherwise the frequency of the noise that breaks it up
+    noise_frequency: f32,
+    // How far inside the volume's edge the density takes to reach its full value
+    edge_softness: f32,
+    // The Henyey-Greenstein phase, positive scatters light forwards
+    anisotropy: f32,
+    // How fast the noise moves through the volume, like wind through smoke
+    drift: vec3<f32>,
+};
+
+struct ShaderFog {
+    // Exponential fog, that thickens evenly with distance
+    density: f32,
+    // Height fog, that is thickest below the height and thins out above it
+    height_density: f32,
+    height: f32,
+    height_falloff: f32,
+    colour: vec3<f32>,
+    // Use the sky's horizon colour instead of the colour
+    match_sky: u32,
+    // How much the fog glows around the sun
+    sun_scattering: f32,
+    volume_steps: u32,
+    volumes_len: u32,
+    volumes: array<ShaderVolume, max_volumes>,
+};
+
+// Fade a surface's colour into the fog, the sky is left alone as it is infinitely far away
+fn apply_fog(colour: vec3<f32>, ray_origin: vec3<f32>, ray_dir: vec3<f32>, dist: f32) -> vec3<f32> {
+    var optical_depth = fog.density * dist;
+
+    // The integral of the height fog's density, which falls off exponentially, along the ray
+    if fog.height_density > 0. {
+        let falloff = max(fog.height_falloff, 1e-4);
+        let start_density = fog.height_density * exp(-falloff * (ray_origin.y - fog.height));
+        let climb = ray_dir.y * falloff;
+
+        if abs(climb) > 1e-5 {
+            optical_depth += start_density * (1. - exp(-dist * climb)) / climb;
+        } else {
+            optical_depth += start_density * dist;
+        }
+    }
+
+    let transmittance = exp(-max(optical_depth, 0.));
+
+    var fog_colour = fog.colour;
+    if fog.match_sky != 0u {
+        fog_colour = sky_fog_colour(ray_dir);
+    }
+    let sun_glow = pow(max(dot(ray_dir, sky.sun_direction), 0.), 8.) * fog.sun_scattering;
+    fog_colour += sky.sun_colour * sun_glow;
+
+    return mix(fog_colour, colour, transmittance);
+}
+
+// March through the volumes between the ray origin and dist, scattering light into the ray and absorbing what is
+// behind them
+fn apply_volumes(colour: vec3<f32>, ray_origin: vec3<f32>, ray_dir: vec3<f32>, dist: f32, light: ShaderLight, time: f32) -> vec3<f32> {
+    // Only march the section of the ray that passes through the volumes' bounding spheres
+    var start = dist;
+    var end = 0.;
+    for (var i = 0u; i < min(fog.volumes_len, u32(max_volumes)); i++) {
+        let volume = fog.volumes[i];
+        let bounds = ray_sphere(ray_origin - volume.pos, ray_dir, volume_radius(volume));
+
+        if bounds.x <= bounds.y {
+            start = min(start, max(bounds.x, 0.));
+            end = max(end, min(bounds.y, dist));
+        }
+    }
+
+    if start >= end {
+        return colour;
+    }
+
+    let step_size = (end - start) / f32(max(fog.volume_steps, 1u));
+    var transmittance = 1.;
+    var scattered = vec3<f32>(0.);
+
+    for (var t = start + step_size * 0.5; t < end; t += step_size) {
+        let p = ray_origin + ray_dir * t;
+        let volume = volume_sample(p, time);
+        let density = volume.w;
+
+        if density <= 1e-4 {
+            continue;
+        }
+
+        // Light from the light source, shadowed by the volumes in front of it, and from the sky. Scaled by pi like
+        // the surface lighting, so the light's colour means the same thing for both
+        let light_dir = light_direction(p, light);
+        let light_transmittance = exp(-volume_optical_depth(p, light_dir, time));
+        let phase = henyey_greenstein(dot(ray_dir, light_dir), volume_anisotropy(p));
+        let in_light = (light.colour * light_transmittance * phase + sky_ambient(vec3<f32>(0., 1., 0.)) / (4. * pi)) * pi;
+
+        // Integrate the scattering over the step, so it conserves energy however large the steps are
+        let step_transmittance = exp(-density * step_size);
+        let in_scattered = volume.rgb * in_light * (1. - step_transmittance);
+        scattered += transmittance * in_scattered;
+        transmittance *= step_transmittance;
+
+        if transmittance < 0.01 {
+            break;
+        }
+    }
+
+    return colour * transmittance + scattered;
+}
+
+// The combined scattering colour (rgb) and density (w) of all the volumes at a point
+fn volume_sample(p: vec3<f32>, time: f32) -> vec4<f32> {
+    var colour = vec3<f32>(0.);
+    var density = 0.;
+
+    for (var i = 0u; i < min(fog.volumes_len, u32(max_volumes)); i++) {
+        let volume = fog.volumes[i];
+        let local_p = p - volume.pos;
+
+        // Fade in from the edge of the volume's shape
+        var volume_density = volume.density * clamp(-volume_sdf(local_p, volume) / max(volume.edge_softness, 1e-4), 0., 1.);
+        if volume_density <= 0. {
+            continue;
+        }
+
+        if volume.noise_frequency > 0. {
+            let noise = perlin_fbm_3d((local_p - volume.drift * time) * volume.noise_frequency, 3u, 2., 0.5);
+            volume_density *= clamp(noise * 2. + 0.5, 0., 1.);
+        }
+
+        colour += volume.colour * volume_density;
+        density += volume_density;
+    }
+
+    if density > 0. {
+        colour /= density;
+    }
+
+    return vec4<f32>(colour, density);
+}
+
+// How much of the volumes there is between a point and the light
+fn volume_optical_depth(p: vec3<f32>, light_dir: vec3<f32>, time: f32) -> f32 {
+    var depth = 0.;
+    var max_radius = 0.;
+    for (var i = 0u; i < min(fog.volumes_len, u32(max_volumes)); i++) {
+        max_radius = max(max_radius, volume_radius(fog.volumes[i]));
+    }
+
+    let step_size = max_radius / f32(volume_light_steps);
+    for (var i = 0; i < volume_light_steps; i++) {
+        depth += volume_sample(p + light_dir * (f32(i) + 0.5) * step_size, time).w * step_size;
+    }
+
+    return depth;
+}
+
+// Anisotropy is taken from the densest volume at the point, so overlapping volumes don't need blending
+fn volume_anisotropy(p: vec3<f32>) -> f32 {
+    var anisotropy = 0.;
+    var closest = 9999.;
+    for (var i = 0u; i < min(fog.volumes_len, u32(max_volumes)); i++) {
+        let dist = volume_sdf(p - fog.volumes[i].pos, fog.volumes[i]);
+        if dist < closest {
+            closest = dist;
+            anisotropy = fog.volumes[i].anisotropy;
+        }
+    }
+
+    return anisotropy;
+}
+
+fn volume_sdf(p: vec3<f32>, volume: ShaderVolume) -> f32 {
+    if volume.shape == 1u {
+        return sdf_box(p, volume.size);
+    }
+
+    return length(p) - volume.size.x;
+}
+
+fn volume_radius(volume: ShaderVolume) -> f32 {
+    if volume.shape == 1u {
+        return length(volume.size);
+    }
+
+    return volume.size.x;
+}
+
+fn henyey_greenstein(cos_theta: f32, g: f32) -> f32 {
+    let g2 = g * g;
+
+    return (1. - g2) / (4. * pi * pow(max(1. + g2 - 2. * g * cos_theta, 1e-4), 1.5));
+}
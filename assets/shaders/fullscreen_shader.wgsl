@@ -6,28 +6,121 @@
 }
 
 #import ray_marching::inputs::material;
-#import ray_marching::ray::{get_ray_dir, ray_march, GetDistanceInput, get_ray_dir_with_fragment_camera};
-#import ray_marching::lighting::get_light;
+#import ray_marching::ray::{ray_march, GetDistanceInput, get_ray_dir_with_fragment_camera, get_shape_colour, shapes};
+#import ray_marching::lighting::{get_light, get_pbr_light, get_normal};
 #import ray_marching::maths::{centre_and_scale_uv_positions, rotate_position};
+#import ray_marching::tonemapping::tonemap;
+#import ray_marching::fog::{apply_fog, apply_volumes};
+#import ray_marching::outline::{is_outline, toon_shade};
+#import ray_marching::debug::debug_colour;
+#import ray_marching::stereo::get_eye_ray;
 
 @fragment
 fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
-    let coords = centre_and_scale_uv_positions(in.position.xy, view.viewport.zw);
-    let camera_pos = material.camera.pos;
+    // Captures render a tile of a larger image, so use the size of the whole image when it is given
+    var frag_pos = in.position.xy;
+    var screen_size = view.viewport.zw;
+    if material.viewport_size.x > 0. {
+        frag_pos += material.viewport_offset;
+        screen_size = material.viewport_size;
+    }
 
-    let ray_dir = get_ray_dir(material.camera, coords);
+    let left_eye = select(-1., 1., material.stereo.swap_eyes != 0u);
+
+    switch material.stereo.mode {
+        // Side by side, each eye gets half of the screen
+        case 1u: {
+            let half_size = vec2<f32>(screen_size.x * 0.5, screen_size.y);
+            if frag_pos.x < half_size.x {
+                return vec4<f32>(render(frag_pos, half_size, left_eye), 1.);
+            }
+            return vec4<f32>(render(frag_pos - vec2<f32>(half_size.x, 0.), half_size, -left_eye), 1.);
+        }
+        // Anaglyph, the left eye in red and the right eye in green and blue
+        case 2u: {
+            let left = render(frag_pos, screen_size, left_eye);
+            let right = render(frag_pos, screen_size, -left_eye);
+            return vec4<f32>(left.r, right.g, right.b, 1.);
+        }
+        default: {
+            return vec4<f32>(render(frag_pos, screen_size, 0.), 1.);
+        }
+    }
+}
+
+// The colour of a pixel seen from an eye, see get_eye_ray, from its position in pixels on a screen of the given size
+fn render(frag_pos: vec2<f32>, screen_size: vec2<f32>, eye: f32) -> vec3<f32> {
+    let coords = centre_and_scale_uv_positions(frag_pos, screen_size);
+    // Rays start at the near distance, and orthographic rays start across the screen
+    let ray = get_eye_ray(material.camera, material.stereo, eye, coords);
+    let ray_origin = ray.origin;
+    let ray_dir = ray.dir;
     let get_dist_input = GetDistanceInput(material.union_type, material.smoothness_val, material.time);
 
-    let ray_march_out = ray_march(camera_pos, ray_dir, get_dist_input);
+    let ray_march_out = ray_march(ray_origin, ray_dir, get_dist_input);
+
+    let point_on_surface: vec3<f32> = ray_origin + ray_dir * ray_march_out.dist;
+
+    // Calculate an estimate of the normal using approximate derivatives
+    let normal = get_normal(point_on_surface, get_dist_input);
+
+    if material.debug.view != 0u {
+        return debug_colour(material.debug, ray_origin, ray_dir, ray_march_out, normal, material.light, get_dist_input);
+    }
+
+    var object_colour = ray_march_out.object_colour;
+    if ray_march_out.hit {
+        object_colour = get_shape_colour(point_on_surface, normal, object_colour, ray_march_out.shape_index, material.time);
+    }
+
+    var colour: vec3<f32>;
+    if !ray_march_out.hit {
+        // The sky is already radiance
+        colour = object_colour;
+    } else if material.shading_model == 0u {
+        // Phong
+        let light_strength = get_light(point_on_surface, normal, -ray_dir, material.light, get_dist_input);
+        colour = object_colour * material.light.colour * light_strength;
+    } else {
+        // Physically based, with Lambert (1) or Burley (2) diffuse
+        let shape_material = shapes[ray_march_out.shape_index].material;
+        colour = get_pbr_light(
+            point_on_surface, normal, -ray_dir, material.light, object_colour, shape_material.metallic,
+            shape_material.roughness, material.shading_model == 2u, get_dist_input
+        );
+    }
+
+    if ray_march_out.hit && material.outline.toon_bands > 0u {
+        colour = toon_shade(colour, material.outline.toon_bands, material.exposure);
+    }
+
+    // Lighten the shape under the cursor, and give the selected shape a coloured rim
+    if ray_march_out.hit {
+        let shape_id = ray_march_out.shape_index + 1u;
+        let rim = pow(1. - max(dot(normal, -ray_dir), 0.), 2.);
+
+        if shape_id == material.selected_shape {
+            colour = mix(colour, vec3<f32>(1., 0.6, 0.1), 0.2 + 0.6 * rim);
+        }
+        if shape_id == material.hovered_shape {
+            colour = mix(colour, vec3<f32>(1.), 0.2);
+        }
+    }
 
-    let point_on_surface: vec3<f32> = camera_pos + ray_dir * ray_march_out.dist;
-    let light_strength = get_light(point_on_surface, -ray_dir, material.light.pos, get_dist_input);
+    // The size of a pixel in the coordinates rays are made from
+    let pixel_size = 2. / min(screen_size.x, screen_size.y);
+    let outlined = is_outline(material.outline, material.camera, material.stereo, eye, coords, pixel_size, ray_march_out, normal, get_dist_input);
 
-    var colour: vec3<f32> = ray_march_out.object_colour * material.light.colour * light_strength;
+    if ray_march_out.hit {
+        colour = apply_fog(colour, ray_origin, ray_dir, ray_march_out.dist);
+    }
+    colour = apply_volumes(colour, ray_origin, ray_dir, ray_march_out.dist, material.light, material.time);
 
-    // Gamma correction
-    let gamma = 2.2;
-    colour = pow(colour, vec3<f32>(1.0 / gamma));
+    // Outlines are drawn in exactly the colour that was picked, over the fog and without tone mapping
+    if outlined {
+        return material.outline.colour;
+    }
 
-    return vec4<f32>(colour, 1.0);
+    // The lighting is unbounded, so bring it into the display's range
+    return tonemap(colour, material.exposure, material.tonemapper);
 }
@@ -3,6 +3,9 @@
 #import ray_marching::shapes::Shape;
 #import ray_marching::lighting::ShaderLight;
 #import ray_marching::ray::ShaderCamera;
+#import ray_marching::outline::ShaderOutline;
+#import ray_marching::debug::ShaderDebug;
+#import ray_marching::stereo::ShaderStereo;
 // #import ray_marching::maths::rotate_position;
 
 @group(2) @binding(0)
@@ -14,6 +17,18 @@ struct ShaderMat {
     light: ShaderLight,
     camera: ShaderCamera,
     time: f32,
+    viewport_offset: vec2<f32>,
+    viewport_size: vec2<f32>,
+    shading_model: u32,
+    // Stops to brighten or darken the image by before tone mapping
+    exposure: f32,
+    tonemapper: u32,
+    // 0 for none, otherwise the shape's index plus one
+    hovered_shape: u32,
+    selected_shape: u32,
+    outline: ShaderOutline,
+    stereo: ShaderStereo,
+    debug: ShaderDebug,
 };
 
 
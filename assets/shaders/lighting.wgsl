@@ -1,32 +1,40 @@
 #define_import_path ray_marching::lighting
 
 #import ray_marching::ray::{get_distance, ray_march, GetDistanceInput};
+#import ray_marching::sky::{sky, sky_ambient};
+
+const pi: f32 = 3.14159265;
 
 struct ShaderLight {
     pos: vec3<f32>,
     colour: vec3<f32>,
+    // 0 for a point light at pos, 1 for a directional light shining from the sky's sun
+    light_type: u32,
+}
+
+// The direction from a point towards the light
+fn light_direction(p: vec3<f32>, light: ShaderLight) -> vec3<f32> {
+    if light.light_type == 1u {
+        return sky.sun_direction;
+    }
+
+    return normalize(light.pos - p);
 }
 
-fn get_light(p: vec3<f32>, view_dir: vec3<f32>, light_pos: vec3<f32>, get_dist_input: GetDistanceInput) -> f32 {
+fn get_light(p: vec3<f32>, normal: vec3<f32>, view_dir: vec3<f32>, light_source: ShaderLight, get_dist_input: GetDistanceInput) -> f32 {
     var diffuse_final = 1.;
     var specular_final = 1.;
 
     let specular_pow = 16.;
     let ambient_strength = 0.1;
 
-    // Get the direction of the light, and calculate an estimate of the normal using approximate derivatives
-    let light = normalize(light_pos - p);
-    let normal = get_normal(p, get_dist_input);
+    // Get the direction of the light
+    let light = light_direction(p, light_source);
 
     // Diffuse lighting depending on the angle the light makes to the surface
     var diffuse = clamp(dot(normal, light), 0., 1.);
 
-    // Ray march from the surface (with slight pertubation to stop clipping through) to the light, to see if there is anything blocking this ray
-    let d = ray_march(p + normal, light, get_dist_input).dist;
-    if d < length(light) {
-        // Reduce the light if there is something in the way
-        diffuse *= 0.1;
-    }
+    diffuse *= get_shadow(p, normal, light, get_dist_input);
 
     diffuse_final *= diffuse;
 
@@ -38,6 +46,107 @@ fn get_light(p: vec3<f32>, view_dir: vec3<f32>, light_pos: vec3<f32>, get_dist_i
     return clamp(diffuse_final, 0., 1.) + clamp(specular_final, 0., 1.) + ambient_strength;
 }
 
+// Physically based lighting using the metallic/roughness workflow, returning the radiance towards the viewer
+fn get_pbr_light(
+    p: vec3<f32>,
+    normal: vec3<f32>,
+    view_dir: vec3<f32>,
+    light: ShaderLight,
+    albedo: vec3<f32>,
+    metallic: f32,
+    roughness: f32,
+    burley_diffuse: bool,
+    get_dist_input: GetDistanceInput,
+) -> vec3<f32> {
+    // Very low roughness makes the highlight too small to see
+    let alpha_roughness = clamp(roughness, 0.045, 1.);
+
+    let light_dir = light_direction(p, light);
+    let half_dir = normalize(view_dir + light_dir);
+
+    let n_dot_v = max(dot(normal, view_dir), 1e-4);
+    let n_dot_l = clamp(dot(normal, light_dir), 0., 1.);
+    let n_dot_h = clamp(dot(normal, half_dir), 0., 1.);
+    let l_dot_h = clamp(dot(light_dir, half_dir), 0., 1.);
+
+    // Metals have no diffuse, and tint their reflections with their colour
+    let f0 = mix(vec3<f32>(0.04), albedo, metallic);
+    let diffuse_colour = albedo * (1. - metallic);
+
+    // Cook-Torrance microfacet specular, with the GGX distribution
+    let fresnel = fresnel_schlick(l_dot_h, f0);
+    let specular = distribution_ggx(n_dot_h, alpha_roughness) * visibility_smith_ggx(n_dot_v, n_dot_l, alpha_roughness) * fresnel;
+
+    var diffuse: vec3<f32>;
+    if burley_diffuse {
+        diffuse = diffuse_colour * diffuse_burley(n_dot_v, n_dot_l, l_dot_h, alpha_roughness);
+    } else {
+        diffuse = diffuse_colour / pi;
+    }
+
+    // The light has no falloff, and is scaled so a white diffuse surface facing it is the light's colour (matching Phong)
+    let direct = (diffuse * (1. - fresnel) + specular) * light.colour * n_dot_l * pi * get_shadow(p, normal, light_dir, get_dist_input);
+
+    // Image-based ambient lighting from the sky, blurring the reflection towards the irradiance as it gets rougher
+    let env_brdf = env_brdf_approx(f0, alpha_roughness, n_dot_v);
+    let irradiance = max(sky_ambient(normal), vec3<f32>(0.));
+    let reflection = max(mix(sky_ambient(reflect(-view_dir, normal)), irradiance, alpha_roughness), vec3<f32>(0.));
+    let ambient = diffuse_colour * irradiance * (1. - env_brdf) + reflection * env_brdf;
+
+    return direct + ambient;
+}
+
+// Ray march from the surface (with slight pertubation to stop clipping through) to the light, to see if there is anything blocking this ray
+fn get_shadow(p: vec3<f32>, normal: vec3<f32>, light_dir: vec3<f32>, get_dist_input: GetDistanceInput) -> f32 {
+    let d = ray_march(p + normal, light_dir, get_dist_input).dist;
+    if d < length(light_dir) {
+        // Reduce the light if there is something in the way
+        return 0.1;
+    }
+
+    return 1.;
+}
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a2 = roughness * roughness * roughness * roughness;
+    let d = n_dot_h * n_dot_h * (a2 - 1.) + 1.;
+
+    return a2 / (pi * d * d);
+}
+
+// The height-correlated Smith masking-shadowing term, divided by the denominator of the specular BRDF
+fn visibility_smith_ggx(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let a2 = roughness * roughness * roughness * roughness;
+    let ggx_v = n_dot_l * sqrt(n_dot_v * n_dot_v * (1. - a2) + a2);
+    let ggx_l = n_dot_v * sqrt(n_dot_l * n_dot_l * (1. - a2) + a2);
+
+    return 0.5 / max(ggx_v + ggx_l, 1e-6);
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+    return f0 + (1. - f0) * pow(1. - cos_theta, 5.);
+}
+
+// Disney's diffuse, which gets brighter at grazing angles on rough surfaces
+fn diffuse_burley(n_dot_v: f32, n_dot_l: f32, l_dot_h: f32, roughness: f32) -> f32 {
+    let f90 = 0.5 + 2. * roughness * l_dot_h * l_dot_h;
+    let light_scatter = 1. + (f90 - 1.) * pow(1. - n_dot_l, 5.);
+    let view_scatter = 1. + (f90 - 1.) * pow(1. - n_dot_v, 5.);
+
+    return light_scatter * view_scatter / pi;
+}
+
+// Karis' analytic fit of the split-sum environment BRDF, to avoid needing a lookup texture
+fn env_brdf_approx(f0: vec3<f32>, roughness: f32, n_dot_v: f32) -> vec3<f32> {
+    let c0 = vec4<f32>(-1., -0.0275, -0.572, 0.022);
+    let c1 = vec4<f32>(1., 0.0425, 1.04, -0.04);
+    let r = roughness * c0 + c1;
+    let a004 = min(r.x * r.x, exp2(-9.28 * n_dot_v)) * r.x + r.y;
+    let ab = vec2<f32>(-1.04, 1.04) * a004 + r.zw;
+
+    return f0 * ab.x + ab.y;
+}
+
 fn get_normal(p: vec3<f32>, get_dist_input: GetDistanceInput) -> vec3<f32> {
     let distance = get_distance(p, get_dist_input).dist;
     let e = vec2<f32>(0.01,0.0); // Epsilon value
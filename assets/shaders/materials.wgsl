@@ -0,0 +1,111 @@
+#define_import_path ray_marching::materials
+
+#import ray_marching::noise::perlin_2d;
+
+// Image textures which shapes can use, unused slots are filled with a white image
+@group(2) @binding(4)
+var texture_0: texture_2d<f32>;
+@group(2) @binding(5)
+var texture_0_sampler: sampler;
+@group(2) @binding(6)
+var texture_1: texture_2d<f32>;
+@group(2) @binding(7)
+var texture_1_sampler: sampler;
+
+struct ShapeMaterial {
+    pattern_colour: vec3<f32>,
+    pattern: u32,
+    pattern_scale: f32,
+    // 0 for no texture, otherwise the texture slot plus one
+    texture: u32,
+    texture_scale: f32,
+    metallic: f32,
+    roughness: f32,
+};
+
+// Add a pattern and texture on top of a shape's colour, where p is relative to the shape's centre
+fn apply_material(colour: vec3<f32>, p: vec3<f32>, normal: vec3<f32>, material: ShapeMaterial) -> vec3<f32> {
+    var final_colour = colour;
+
+    if material.pattern != 0u {
+        let amount = triplanar_pattern(p / max(material.pattern_scale, 1e-6), normal, material.pattern);
+        final_colour = mix(final_colour, material.pattern_colour, amount);
+    }
+
+    if material.texture != 0u {
+        final_colour *= triplanar_texture(p / max(material.texture_scale, 1e-6), normal, material.texture);
+    }
+
+    return final_colour;
+}
+
+// How much each axis' projection contributes, sharpened so the blend between them is narrow
+fn triplanar_weights(normal: vec3<f32>) -> vec3<f32> {
+    let weights = pow(abs(normal), vec3<f32>(4.));
+
+    return weights / (weights.x + weights.y + weights.z);
+}
+
+// Project the pattern along each axis, and blend them depending on the way the surface faces
+fn triplanar_pattern(p: vec3<f32>, normal: vec3<f32>, pattern: u32) -> f32 {
+    let weights = triplanar_weights(normal);
+
+    return pattern_2d(p.zy, pattern) * weights.x + pattern_2d(p.xz, pattern) * weights.y + pattern_2d(p.xy, pattern) * weights.z;
+}
+
+fn triplanar_texture(p: vec3<f32>, normal: vec3<f32>, texture: u32) -> vec3<f32> {
+    let weights = triplanar_weights(normal);
+
+    return sample_texture(p.zy, texture) * weights.x + sample_texture(p.xz, texture) * weights.y + sample_texture(p.xy, texture) * weights.z;
+}
+
+// Returns how much of the pattern colour to use, from 0 to 1
+fn pattern_2d(uv: vec2<f32>, pattern: u32) -> f32 {
+    switch pattern {
+        // Checker
+        case (1u) {
+            let cell = floor(uv);
+
+            return abs(cell.x + cell.y) % 2.;
+        }
+        // Stripes
+        case (2u) {
+            return step(0.5, fract(uv.x));
+        }
+        // Noise
+        case (3u) {
+            return clamp(perlin_2d(0u, uv) + 0.5, 0., 1.);
+        }
+        // Grid
+        case (4u) {
+            let line_width = 0.05;
+            let to_line = abs(fract(uv + 0.5) - 0.5);
+
+            return select(0., 1., min(to_line.x, to_line.y) < line_width);
+        }
+        // Gradient, from the bottom to the top of each unit
+        case (5u) {
+            return fract(uv.y);
+        }
+        default {
+            return 0.;
+        }
+    }
+}
+
+// Textures repeat every unit, and are sampled at the top mip level as the ray march isn't uniform control flow
+fn sample_texture(uv: vec2<f32>, texture: u32) -> vec3<f32> {
+    let wrapped = fract(uv);
+
+    switch texture {
+        case (1u) {
+            return textureSampleLevel(texture_0, texture_0_sampler, wrapped, 0.).rgb;
+        }
+        case (2u) {
+            return textureSampleLevel(texture_1, texture_1_sampler, wrapped, 0.).rgb;
+        }
+        default {
+            return vec3<f32>(1.);
+        }
+    }
+}
@@ -0,0 +1,180 @@
+#define_import_path ray_marching::modifiers
+
+#import ray_marching::noise::{perlin_3d, perlin_fbm_3d, simplex_3d, voronoi_3d, fractal_bounding};
+
+struct ShapeDomain {
+    repetition: u32,
+    period: vec3<f32>,
+    limit: vec3<f32>,
+    polar_count: u32,
+    polar_radius: f32,
+    mirror: u32,
+    deform: u32,
+    deform_amount: f32,
+};
+
+struct ShapeDisplacement {
+    displacement_type: u32,
+    amplitude: f32,
+    frequency: f32,
+    octaves: u32,
+};
+
+const fbm_lacunarity: f32 = 2.;
+const fbm_gain: f32 = 0.5;
+// The steepest each noise gets, a little above the largest gradients found by searching the CPU noise
+const perlin_max_gradient: f32 = 3.5;
+const simplex_max_gradient: f32 = 10.;
+// Voronoi distances and the sine product can't change faster than this
+const default_max_gradient: f32 = 2.;
+
+// Move a point into the shape's mirrored, repeated and deformed space, mirroring is across the world axes so the shape
+// gets a copy on the other side, while repetition and deformation are around the shape's centre
+fn apply_domain(p: vec3<f32>, centre: vec3<f32>, domain: ShapeDomain) -> vec3<f32> {
+    var q = mirror(p, domain.mirror) - centre;
+
+    switch domain.repetition {
+        case(1u) {
+            q = repeat(q, domain.period);
+        }
+        case(2u) {
+            q = repeat_limited(q, domain.period, domain.limit);
+        }
+        case(3u) {
+            q = repeat_polar(q, domain.polar_count, domain.polar_radius);
+        }
+        default {}
+    }
+
+    switch domain.deform {
+        case(1u) {
+            q = twist(q, domain.deform_amount);
+        }
+        case(2u) {
+            q = bend(q, domain.deform_amount);
+        }
+        default {}
+    }
+
+    return q + centre;
+}
+
+// Twisting and bending stretch space, so the ray has to take smaller steps to stop it overshooting the surface. They
+// turn the shape by an angle which changes by the amount per unit, moving its surface by up to that times its radius
+fn domain_step_scale(domain: ShapeDomain, radius: f32) -> f32 {
+    if domain.deform == 0u {
+        return 1.;
+    }
+
+    return 1. / (1. + abs(domain.deform_amount) * radius);
+}
+
+// Noise added to the shape's distance, sampled relative to the shape's centre so it moves with the shape
+fn displacement(p: vec3<f32>, centre: vec3<f32>, displacement: ShapeDisplacement) -> f32 {
+    let q = (p - centre) * displacement.frequency;
+
+    var noise: f32;
+    switch displacement.displacement_type {
+        case(1u) {
+            noise = perlin_3d(0u, q);
+        }
+        case(2u) {
+            noise = simplex_3d(0u, q);
+        }
+        case(3u) {
+            noise = perlin_fbm_3d(q, displacement.octaves, fbm_lacunarity, fbm_gain);
+        }
+        case(4u) {
+            noise = voronoi_3d(q);
+        }
+        case(5u) {
+            noise = sin(q.x) * sin(q.y) * sin(q.z);
+        }
+        default {
+            return 0.;
+        }
+    }
+
+    return noise * displacement.amplitude;
+}
+
+// Displacement makes the distance change faster than the distance to the surface, so the ray has to take smaller steps
+// (one over an estimate of the Lipschitz constant of the displaced distance)
+fn displacement_step_scale(displacement: ShapeDisplacement) -> f32 {
+    if displacement.displacement_type == 0u {
+        return 1.;
+    }
+
+    var max_gradient = default_max_gradient;
+    switch displacement.displacement_type {
+        case (1u) {
+            max_gradient = perlin_max_gradient;
+        }
+        case (2u) {
+            max_gradient = simplex_max_gradient;
+        }
+        // Each octave of fBm adds the same gradient, as the frequency doubles while the amplitude halves
+        case (3u) {
+            max_gradient = perlin_max_gradient * f32(displacement.octaves)
+                * fractal_bounding(displacement.octaves, fbm_gain);
+        }
+        default {}
+    }
+
+    return 1. / (1. + abs(displacement.amplitude) * displacement.frequency * max_gradient);
+}
+
+// Reflect the point across the planes whose bits are set (x = 1, y = 2, z = 4)
+fn mirror(p: vec3<f32>, axes: u32) -> vec3<f32> {
+    let mirrored = vec3<bool>((axes & 1u) != 0u, (axes & 2u) != 0u, (axes & 4u) != 0u);
+
+    return select(p, abs(p), mirrored);
+}
+
+// Repeat space forever along each axis with a non-zero period
+fn repeat(p: vec3<f32>, period: vec3<f32>) -> vec3<f32> {
+    let repeated = period > vec3<f32>(0.);
+    let safe_period = select(vec3<f32>(1.), period, repeated);
+
+    return select(p, p - safe_period * round(p / safe_period), repeated);
+}
+
+// Repeat space along each axis with a non-zero period, up to the limit of copies either side of the original
+fn repeat_limited(p: vec3<f32>, period: vec3<f32>, limit: vec3<f32>) -> vec3<f32> {
+    let repeated = period > vec3<f32>(0.);
+    let safe_period = select(vec3<f32>(1.), period, repeated);
+
+    return select(p, p - safe_period * clamp(round(p / safe_period), -limit, limit), repeated);
+}
+
+// Repeat space in a ring around the Y axis, with each copy the given radius from the centre
+fn repeat_polar(p: vec3<f32>, count: u32, radius: f32) -> vec3<f32> {
+    if count == 0u {
+        return p;
+    }
+
+    let sector = 6.2831853 / f32(count);
+    let angle = atan2(p.z, p.x);
+    let folded_angle = angle - sector * round(angle / sector);
+    let r = length(p.xz);
+
+    return vec3<f32>(r * cos(folded_angle) - radius, p.y, r * sin(folded_angle));
+}
+
+// Rotate around the Y axis by an angle which increases with height
+fn twist(p: vec3<f32>, amount: f32) -> vec3<f32> {
+    let angle = amount * p.y;
+    let c = cos(angle);
+    let s = sin(angle);
+
+    return vec3<f32>(c * p.x - s * p.z, p.y, s * p.x + c * p.z);
+}
+
+// Curve the X axis upwards, rotating in the XY plane by an angle which increases along X
+fn bend(p: vec3<f32>, amount: f32) -> vec3<f32> {
+    let angle = amount * p.x;
+    let c = cos(angle);
+    let s = sin(angle);
+
+    return vec3<f32>(c * p.x - s * p.y, s * p.x + c * p.y, p.z);
+}
@@ -0,0 +1,225 @@
+#define_import_path ray_marching::noise
+
+// A port of the parts of bracket-noise's FastNoise used by the shader, so the CPU and GPU produce the same noise.
+// The permutation table is generated on the CPU from the same seed as the CPU's FastNoise
+@group(2) @binding(3)
+var<storage> noise_permutation: array<u32>;
+
+var<private> grad_x: array<f32, 12> = array<f32, 12>(1., -1., 1., -1., 1., -1., 1., -1., 0., 0., 0., 0.);
+var<private> grad_y: array<f32, 12> = array<f32, 12>(1., 1., -1., -1., 0., 0., 0., 0., 1., -1., 1., -1.);
+var<private> grad_z: array<f32, 12> = array<f32, 12>(0., 0., 0., 0., 1., 1., -1., -1., 1., 1., -1., -1.);
+
+const F3: f32 = 1. / 3.;
+const G3: f32 = 1. / 6.;
+
+// Matches FastNoise, which rounds exact negative integers down an extra step
+fn fast_floor(f: f32) -> i32 {
+    if f >= 0. {
+        return i32(f);
+    }
+
+    return i32(f) - 1;
+}
+
+fn interp_quintic(t: f32) -> f32 {
+    return t * t * t * (t * (t * 6. - 15.) + 10.);
+}
+
+fn index2d_256(offset: u32, x: i32, y: i32) -> u32 {
+    let py = noise_permutation[(u32(y) & 255u) + offset];
+
+    return noise_permutation[(u32(x) & 255u) + py];
+}
+
+fn index3d_256(offset: u32, x: i32, y: i32, z: i32) -> u32 {
+    let pz = noise_permutation[(u32(z) & 255u) + offset];
+    let py = noise_permutation[(u32(y) & 255u) + pz];
+
+    return noise_permutation[(u32(x) & 255u) + py];
+}
+
+fn grad_coord_2d(offset: u32, x: i32, y: i32, xd: f32, yd: f32) -> f32 {
+    let lut_pos = index2d_256(offset, x, y) % 12u;
+
+    return xd * grad_x[lut_pos] + yd * grad_y[lut_pos];
+}
+
+fn grad_coord_3d(offset: u32, x: i32, y: i32, z: i32, d: vec3<f32>) -> f32 {
+    let lut_pos = index3d_256(offset, x, y, z) % 12u;
+
+    return d.x * grad_x[lut_pos] + d.y * grad_y[lut_pos] + d.z * grad_z[lut_pos];
+}
+
+// FastNoise's fractal noise is normalised by a bound which includes one more octave than is summed
+fn fractal_bounding(octaves: u32, gain: f32) -> f32 {
+    var amp = gain;
+    var amp_fractal = 1.;
+
+    for (var i = 0u; i < octaves; i++) {
+        amp_fractal += amp;
+        amp *= gain;
+    }
+
+    return 1. / amp_fractal;
+}
+
+fn perlin_2d(offset: u32, p: vec2<f32>) -> f32 {
+    let x0 = fast_floor(p.x);
+    let y0 = fast_floor(p.y);
+    let x1 = x0 + 1;
+    let y1 = y0 + 1;
+
+    let xd0 = p.x - f32(x0);
+    let yd0 = p.y - f32(y0);
+    let xd1 = xd0 - 1.;
+    let yd1 = yd0 - 1.;
+
+    let xs = interp_quintic(xd0);
+    let ys = interp_quintic(yd0);
+
+    let xf0 = mix(grad_coord_2d(offset, x0, y0, xd0, yd0), grad_coord_2d(offset, x1, y0, xd1, yd0), xs);
+    let xf1 = mix(grad_coord_2d(offset, x0, y1, xd0, yd1), grad_coord_2d(offset, x1, y1, xd1, yd1), xs);
+
+    return mix(xf0, xf1, ys);
+}
+
+fn perlin_fbm_2d(p: vec2<f32>, octaves: u32, lacunarity: f32, gain: f32) -> f32 {
+    var q = p;
+    var sum = perlin_2d(noise_permutation[0], q);
+    var amp = 1.;
+
+    for (var i = 1u; i < octaves; i++) {
+        q *= lacunarity;
+        amp *= gain;
+        sum += perlin_2d(noise_permutation[i], q) * amp;
+    }
+
+    return sum * fractal_bounding(octaves, gain);
+}
+
+fn perlin_3d(offset: u32, p: vec3<f32>) -> f32 {
+    let p0 = vec3<i32>(fast_floor(p.x), fast_floor(p.y), fast_floor(p.z));
+    let p1 = p0 + vec3<i32>(1);
+
+    let d0 = p - vec3<f32>(p0);
+    let d1 = d0 - vec3<f32>(1.);
+
+    let s = vec3<f32>(interp_quintic(d0.x), interp_quintic(d0.y), interp_quintic(d0.z));
+
+    let xf00 = mix(
+        grad_coord_3d(offset, p0.x, p0.y, p0.z, d0),
+        grad_coord_3d(offset, p1.x, p0.y, p0.z, vec3<f32>(d1.x, d0.y, d0.z)),
+        s.x
+    );
+    let xf10 = mix(
+        grad_coord_3d(offset, p0.x, p1.y, p0.z, vec3<f32>(d0.x, d1.y, d0.z)),
+        grad_coord_3d(offset, p1.x, p1.y, p0.z, vec3<f32>(d1.x, d1.y, d0.z)),
+        s.x
+    );
+    let xf01 = mix(
+        grad_coord_3d(offset, p0.x, p0.y, p1.z, vec3<f32>(d0.x, d0.y, d1.z)),
+        grad_coord_3d(offset, p1.x, p0.y, p1.z, vec3<f32>(d1.x, d0.y, d1.z)),
+        s.x
+    );
+    let xf11 = mix(
+        grad_coord_3d(offset, p0.x, p1.y, p1.z, vec3<f32>(d0.x, d1.y, d1.z)),
+        grad_coord_3d(offset, p1.x, p1.y, p1.z, d1),
+        s.x
+    );
+
+    return mix(mix(xf00, xf10, s.y), mix(xf01, xf11, s.y), s.z);
+}
+
+fn perlin_fbm_3d(p: vec3<f32>, octaves: u32, lacunarity: f32, gain: f32) -> f32 {
+    var q = p;
+    var sum = perlin_3d(noise_permutation[0], q);
+    var amp = 1.;
+
+    for (var i = 1u; i < octaves; i++) {
+        q *= lacunarity;
+        amp *= gain;
+        sum += perlin_3d(noise_permutation[i], q) * amp;
+    }
+
+    return sum * fractal_bounding(octaves, gain);
+}
+
+fn simplex_corner(offset: u32, cell: vec3<i32>, d: vec3<f32>) -> f32 {
+    var t = 0.6 - dot(d, d);
+    if t < 0. {
+        return 0.;
+    }
+
+    t *= t;
+    return t * t * grad_coord_3d(offset, cell.x, cell.y, cell.z, d);
+}
+
+fn simplex_3d(offset: u32, p: vec3<f32>) -> f32 {
+    var t = (p.x + p.y + p.z) * F3;
+    let cell = vec3<i32>(fast_floor(p.x + t), fast_floor(p.y + t), fast_floor(p.z + t));
+
+    t = f32(cell.x + cell.y + cell.z) * G3;
+    let d0 = p - (vec3<f32>(cell) - t);
+
+    // Find which simplex of the skewed cube the point is in
+    var o1: vec3<i32>;
+    var o2: vec3<i32>;
+    if d0.x >= d0.y {
+        if d0.y >= d0.z {
+            o1 = vec3<i32>(1, 0, 0);
+            o2 = vec3<i32>(1, 1, 0);
+        } else if d0.x >= d0.z {
+            o1 = vec3<i32>(1, 0, 0);
+            o2 = vec3<i32>(1, 0, 1);
+        } else {
+            o1 = vec3<i32>(0, 0, 1);
+            o2 = vec3<i32>(1, 0, 1);
+        }
+    } else {
+        if d0.y < d0.z {
+            o1 = vec3<i32>(0, 0, 1);
+            o2 = vec3<i32>(0, 1, 1);
+        } else if d0.x < d0.z {
+            o1 = vec3<i32>(0, 1, 0);
+            o2 = vec3<i32>(0, 1, 1);
+        } else {
+            o1 = vec3<i32>(0, 1, 0);
+            o2 = vec3<i32>(1, 1, 0);
+        }
+    }
+
+    let d1 = d0 - vec3<f32>(o1) + G3;
+    let d2 = d0 - vec3<f32>(o2) + 2. * G3;
+    let d3 = d0 - 1. + 3. * G3;
+
+    let n0 = simplex_corner(offset, cell, d0);
+    let n1 = simplex_corner(offset, cell + o1, d1);
+    let n2 = simplex_corner(offset, cell + o2, d2);
+    let n3 = simplex_corner(offset, cell + vec3<i32>(1), d3);
+
+    return 32. * (n0 + n1 + n2 + n3);
+}
+
+// Distance to the nearest of a grid of randomly offset points, remapped to roughly -1 to 1 like the other noise
+fn voronoi_3d(p: vec3<f32>) -> f32 {
+    let cell = vec3<i32>(fast_floor(p.x), fast_floor(p.y), fast_floor(p.z));
+    var min_dist_sq = 3.;
+
+    for (var z = -1; z <= 1; z++) {
+        for (var y = -1; y <= 1; y++) {
+            for (var x = -1; x <= 1; x++) {
+                let neighbour = cell + vec3<i32>(x, y, z);
+                let jitter = vec3<f32>(
+                    f32(index3d_256(0u, neighbour.x, neighbour.y, neighbour.z)),
+                    f32(index3d_256(1u, neighbour.x, neighbour.y, neighbour.z)),
+                    f32(index3d_256(2u, neighbour.x, neighbour.y, neighbour.z)),
+                ) / 255.;
+
+                let offset = vec3<f32>(neighbour) + jitter - p;
+                min_dist_sq = min(min_dist_sq, dot(offset, offset));
+            }
+        }
+    }
+
+    return sqrt(min_dist_sq) * 2. - 1.;
+}
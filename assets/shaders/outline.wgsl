@@ -0,0 +1,81 @@
+#define_import_path ray_marching::outline
+
+#import ray_marching::ray::{ShaderCamera, GetDistanceInput, RayMarchOutput, coords_angle, ray_march};
+#import ray_marching::lighting::get_normal;
+#import ray_marching::stereo::{ShaderStereo, get_eye_ray};
+
+struct ShaderOutline {
+    enabled: u32,
+    // In pixels
+    width: f32,
+    colour: vec3<f32>,
+    // Edges where one shape meets another, or a shape meets the sky
+    object_edges: u32,
+    // Creases where the normal changes by more than the threshold angle (as a cosine)
+    normal_edges: u32,
+    normal_threshold: f32,
+    // Quantise the lighting into this many bands, 0 for smooth lighting
+    toon_bands: u32,
+};
+
+// Quantise the brightness of a lit colour into bands, keeping its hue
+fn toon_shade(colour: vec3<f32>, bands: u32, exposure: f32) -> vec3<f32> {
+    // Band the brightness the colour has once it is exposed, clamped to the display's range, so HDR highlights all
+    // fall in the top band rather than making bands of their own
+    let brightness = dot(colour * exp2(exposure), vec3<f32>(0.2126, 0.7152, 0.0722));
+    let banded = ceil(clamp(brightness, 0., 1.) * f32(bands)) / f32(bands);
+
+    return colour * banded / max(brightness, 1e-4);
+}
+
+// Returns whether this pixel is on an outline. Silhouettes come from how closely the ray missed a shape, other edges
+// are found by marching rays one width to the right of and above the pixel and comparing what they hit
+fn is_outline(
+    outline: ShaderOutline,
+    camera: ShaderCamera,
+    stereo: ShaderStereo,
+    eye: f32,
+    coords: vec2<f32>,
+    pixel_size: f32,
+    ray_march_out: RayMarchOutput,
+    normal: vec3<f32>,
+    get_dist_input: GetDistanceInput,
+) -> bool {
+    if outline.enabled == 0u {
+        return false;
+    }
+
+    if !ray_march_out.hit {
+        // Orthographic rays are parallel, so their width doesn't grow with distance
+        if camera.projection == 1u {
+            return ray_march_out.min_dist < outline.width * pixel_size * camera.ortho_size;
+        }
+
+        // The angle the outline covers at the centre of the screen
+        return ray_march_out.min_angle < outline.width * pixel_size * coords_angle(camera);
+    }
+
+    if outline.object_edges == 0u && outline.normal_edges == 0u {
+        return false;
+    }
+
+    var offsets = array<vec2<f32>, 2>(vec2<f32>(1., 0.), vec2<f32>(0., 1.));
+    for (var i = 0; i < 2; i++) {
+        let ray = get_eye_ray(camera, stereo, eye, coords + offsets[i] * outline.width * pixel_size);
+        let neighbour = ray_march(ray.origin, ray.dir, get_dist_input);
+
+        if outline.object_edges != 0u && (!neighbour.hit || neighbour.shape_index != ray_march_out.shape_index) {
+            return true;
+        }
+
+        if outline.normal_edges != 0u && neighbour.hit {
+            let neighbour_normal = get_normal(ray.origin + ray.dir * neighbour.dist, get_dist_input);
+
+            if dot(normal, neighbour_normal) < outline.normal_threshold {
+                return true;
+            }
+        }
+    }
+
+    return false;
+}
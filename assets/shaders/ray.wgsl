@@ -1,7 +1,10 @@
 #define_import_path ray_marching::ray
 
-#import ray_marching::shapes::{Shape, shape_to_sdf, SDFOutput};
-#import ray_marching::maths::smin;
+#import ray_marching::shapes::{Shape, shape_to_sdf, sdf_step_scale, bounding_radius, SDFOutput};
+#import ray_marching::maths::{smin, rotate_position};
+#import ray_marching::modifiers::{apply_domain, domain_step_scale, displacement, displacement_step_scale};
+#import ray_marching::materials::apply_material;
+#import ray_marching::sky::sky_colour;
 
 @group(2) @binding(1)
 var<storage> shapes: array<Shape>;
@@ -12,6 +15,9 @@ var<uniform> shapes_len: u32;
 const max_dist: f32 = 300.;
 const max_steps = 150;
 const epsilon: f32 = 0.01;
+const pi: f32 = 3.14159265;
+// Matches CAMERA_MAX_PERSPECTIVE_FOV, as perspective stretches towards infinity at 180 degrees
+const max_perspective_fov: f32 = 2.96705973;
 
 struct Ray {
     origin: vec3<f32>,
@@ -20,11 +26,16 @@ struct Ray {
 
 struct ShaderCamera {
     pos: vec3<f32>,
-    zoom: f32,
+    // Vertical field of view in radians
+    fov: f32,
     rotation: vec4<f32>,
     forward: vec3<f32>,
+    near: f32,
     right: vec3<f32>,
+    ortho_size: f32,
     up: vec3<f32>,
+    // 0 perspective, 1 orthographic, 2 fisheye, 3 equirectangular
+    projection: u32,
 };
 
 struct GetDistanceInput {
@@ -37,12 +48,21 @@ struct DistanceOutput {
     dist: f32,
     colour: vec3<f32>,
     shape_type: u32,
+    // How much of the distance it is safe to step, for shapes whose distances aren't exact
+    step_scale: f32,
+    shape_index: u32,
 }
 
 struct RayMarchOutput {
     object_colour: vec3<f32>,
     dist: f32,
     min_dist: f32,
+    // Whether the ray ended on a shape, and which shape it was
+    hit: bool,
+    shape_index: u32,
+    // The smallest angle (in radians, roughly) between the ray and the shapes it passed, used to draw silhouettes
+    min_angle: f32,
+    steps: u32,
 };
 
 fn ray_march(ray_origin: vec3<f32>, ray_dir: vec3<f32>, get_dist_input: GetDistanceInput) -> RayMarchOutput {
@@ -50,6 +70,7 @@ fn ray_march(ray_origin: vec3<f32>, ray_dir: vec3<f32>, get_dist_input: GetDista
 
     // Keep track of the minimum distance that the ray reached
     var min_dist = max_dist;
+    var min_angle = 1.;
 
     var ray_dist = 0.;
     var total_ray_dist = ray_dist;
@@ -67,6 +88,7 @@ fn ray_march(ray_origin: vec3<f32>, ray_dir: vec3<f32>, get_dist_input: GetDista
         if dist < min_dist {
             min_dist = dist;
         }
+        min_angle = min(min_angle, dist / max(ray_dist, epsilon));
 
         // Exit the loop if we have traversed for too many iterations
         if march_steps > max_steps {
@@ -85,39 +107,39 @@ fn ray_march(ray_origin: vec3<f32>, ray_dir: vec3<f32>, get_dist_input: GetDista
                 // ray_dist = 0.1;
 
                 min_dist = 0.15;
+                min_angle = 1.;
                 continue;
                 // return RayMarchOutput(vec3<f32>(0., 0., 1.), 1,0.001);
             }
         
-            return RayMarchOutput(object_col, ray_dist, min_dist);
+            return RayMarchOutput(object_col, ray_dist, min_dist, true, dist_output.shape_index, 0., u32(march_steps));
         }
 
         // Move the ray
-        ray.origin += ray.dir * dist;
-        ray_dist += dist;
-        total_ray_dist += dist;
+        let step = dist * dist_output.step_scale;
+        ray.origin += ray.dir * step;
+        ray_dist += step;
+        total_ray_dist += step;
     }
 
-    // Draws an outline of shapes where the ray missed by only a small amount
-    if min_dist < 0.1 {
-        return RayMarchOutput(vec3<f32>(1., 1., 1.), ray_dist, min_dist);
-    }
-
-    let sky_col = vec3<f32>(0.1, 0.2, 0.7);
-    let bottom_sky_col = vec3<f32>(0.3, 0.2, 0.5);
+    let background = sky_colour(ray.dir);
 
-    let background = mix(bottom_sky_col, sky_col,  (1.5 + dot(vec3<f32>(0.,1.,0.), ray.dir)));
- 
     // let background = vec3<f32>(0.0, 0.0, 0.);
     // let background = (ray_dir + 1.) / 2.;
 
-    return RayMarchOutput(background, ray_dist, min_dist);
+    return RayMarchOutput(background, ray_dist, min_dist, false, 0u, min_angle, u32(march_steps));
 }
 
 fn get_distance(p: vec3<f32>, get_dist_input: GetDistanceInput) -> DistanceOutput {
-    var dist: f32;
+    // Far away if every shape is hidden
+    var dist = 9999.;
+    var first_shape = true;
     var colour = vec3<f32>(0.);
     var shape_type: u32 = 0;
+    var shape_index = 0u;
+    // The shapes' distances combined the same way, after each is scaled by its own step scale, so a shape that isn't
+    // the closest still can't be stepped through
+    var step_dist = 9999.;
 
     var closest_or_furthest: f32;
     if get_dist_input.union_type == 0 {
@@ -127,26 +149,19 @@ fn get_distance(p: vec3<f32>, get_dist_input: GetDistanceInput) -> DistanceOutpu
     }
     
     for (var i = 0u; i < shapes_len; i++) {
-        var shape_modified = shapes[i];
-
-
-        if shape_modified.shape_type != 3 { // Isn't a plane
-            // Give different motion depending on index in shapes array
-            if i == 0 {
-                shape_modified.pos.y += 2. * sin(get_dist_input.time);
-            } else if i == 1 {
-                shape_modified.pos.x += 2. * cos(get_dist_input.time * 2.);
-            } else if i == 2 {
-                shape_modified.pos.x += f32(i) * 3.5 * sin(get_dist_input.time * 1.5 / f32(i) + f32(i) * 0.5); 
-                shape_modified.pos.y += f32(i) * 3.5 * cos(get_dist_input.time * 2.5 / f32(i) + f32(i) * 0.5); 
-            } else {
-                // Portal
-
-            }
+        if shapes[i].hidden != 0u {
+            continue;
         }
 
-        // Get the distance to this shape, and its colour
-        let sdf_out = shape_to_sdf(p, shape_modified, get_dist_input.union_type, get_dist_input.time);
+        let shape_modified = animate_shape(shapes[i], get_dist_input.time);
+
+        // Get the distance to this shape, and its colour, with the sample point moved into the shape's repeated space
+        let shape_p = apply_domain(to_shape_space(p, shape_modified), shape_modified.pos, shape_modified.domain);
+        var sdf_out = shape_to_sdf(shape_p, shape_modified, get_dist_input.union_type, get_dist_input.time);
+        sdf_out.dist += displacement(shape_p, shape_modified.pos, shape_modified.displacement);
+        let shape_step_scale = sdf_step_scale(shape_modified)
+            * domain_step_scale(shape_modified.domain, bounding_radius(shape_modified))
+            * displacement_step_scale(shape_modified.displacement);
 
         // If we are finding the minimum of all the shapes, then find closest, otherwise, find furthest
         if get_dist_input.union_type == 0 {
@@ -154,36 +169,151 @@ fn get_distance(p: vec3<f32>, get_dist_input: GetDistanceInput) -> DistanceOutpu
                 closest_or_furthest = sdf_out.dist;
                 colour = sdf_out.colour;
                 shape_type = shape_modified.shape_type;
+                shape_index = i;
             }
         } else if sdf_out.dist > closest_or_furthest {
             closest_or_furthest = sdf_out.dist;
             colour = sdf_out.colour;
             shape_type = shape_modified.shape_type;
+            shape_index = i;
         }
 
         // Min or Max the distances, unless this is the first shape
-        if i == 0 {
+        let shape_step_dist = sdf_out.dist * shape_step_scale;
+        if first_shape {
             dist = sdf_out.dist;
+            step_dist = shape_step_dist;
+            first_shape = false;
         } else {
             switch get_dist_input.union_type {
                 case(1u) {
                     dist = max(dist, sdf_out.dist);
+                    step_dist = max(step_dist, shape_step_dist);
                 }
                 default {
                     dist = smin(dist, sdf_out.dist, get_dist_input.smoothness_val);
+                    step_dist = smin(step_dist, shape_step_dist, get_dist_input.smoothness_val);
                  }
             }
         }
     }
 
-    return DistanceOutput(dist, colour, shape_type);
+    // Both are combined the same way and the scales are at most 1, so this is at most 1 too
+    var step_scale = 1.;
+    if dist > 0. {
+        step_scale = clamp(step_dist / dist, 0., 1.);
+    }
+
+    return DistanceOutput(dist, colour, shape_type, step_scale, shape_index);
+}
+
+fn animate_shape(shape: Shape, time: f32) -> Shape {
+    var shape_modified = shape;
+    let index = shape.id;
+
+    // Isn't a plane or terrain, or moved by physics
+    if shape_modified.shape_type != 3 && shape_modified.shape_type != 9 && shape_modified.dynamic == 0u {
+        // Give different motion depending on the shape's ID, so it keeps its motion when the shapes are reordered
+        if index == 0 {
+            shape_modified.pos.y += 2. * sin(time);
+        } else if index == 1 {
+            shape_modified.pos.x += 2. * cos(time * 2.);
+        } else if index == 2 {
+            shape_modified.pos.x += f32(index) * 3.5 * sin(time * 1.5 / f32(index) + f32(index) * 0.5);
+            shape_modified.pos.y += f32(index) * 3.5 * cos(time * 2.5 / f32(index) + f32(index) * 0.5);
+        }
+    }
+
+    return shape_modified;
+}
+
+// Undo the shape's rotation about its position
+fn to_shape_space(p: vec3<f32>, shape: Shape) -> vec3<f32> {
+    let inverse_rotation = vec4<f32>(-shape.rotation.xyz, shape.rotation.w);
+
+    return rotate_position(p - shape.pos, inverse_rotation) + shape.pos;
+}
+
+// Apply the material of the shape that was hit, in the shape's own space so its pattern moves with it
+fn get_shape_colour(p: vec3<f32>, normal: vec3<f32>, colour: vec3<f32>, shape_index: u32, time: f32) -> vec3<f32> {
+    let shape = animate_shape(shapes[shape_index], time);
+    let object_p = apply_domain(to_shape_space(p, shape), shape.pos, shape.domain) - shape.pos;
+
+    return apply_material(colour, object_p, normal, shape.material);
+}
+
+// The distance to the screen for perspective rays, where it spans -1 to 1 along its shorter axis
+fn focal_length(camera: ShaderCamera) -> f32 {
+    return 1. / tan(min(camera.fov, max_perspective_fov) * 0.5);
 }
 
 fn get_ray_dir(camera: ShaderCamera, uv: vec2<f32>) -> vec3<f32> {
-    let screen_centre = camera.pos + camera.forward * camera.zoom;
-    let intersection_point = screen_centre + uv.x * camera.right + uv.y * camera.up;
+    let across = uv.x * camera.right + uv.y * camera.up;
 
-    return normalize(intersection_point - camera.pos);
+    switch camera.projection {
+        // Orthographic
+        case 1u: {
+            return camera.forward;
+        }
+        // Fisheye
+        case 2u: {
+            let angle = length(uv) * camera.fov * 0.5;
+            var side = vec3<f32>(0.);
+            if length(uv) > 0. {
+                side = normalize(across);
+            }
+            return camera.forward * cos(angle) + side * sin(angle);
+        }
+        // Equirectangular
+        case 3u: {
+            let longitude = uv.x * pi * 0.5;
+            let latitude = uv.y * pi * 0.5;
+            return (camera.forward * cos(longitude) + camera.right * sin(longitude)) * cos(latitude)
+                + camera.up * sin(latitude);
+        }
+        default: {
+            return normalize(camera.forward * focal_length(camera) + across);
+        }
+    }
+}
+
+// Orthographic rays start across the screen, and rays start the near distance in front of the camera, along a plane
+// for perspective and around a sphere for the wide angle projections
+fn get_ray_origin(camera: ShaderCamera, uv: vec2<f32>, ray_dir: vec3<f32>) -> vec3<f32> {
+    switch camera.projection {
+        case 0u: {
+            return camera.pos + ray_dir * camera.near / dot(ray_dir, camera.forward);
+        }
+        case 1u: {
+            return camera.pos + (uv.x * camera.right + uv.y * camera.up) * camera.ortho_size + camera.forward * camera.near;
+        }
+        default: {
+            return camera.pos + ray_dir * camera.near;
+        }
+    }
+}
+
+fn get_ray(camera: ShaderCamera, uv: vec2<f32>) -> Ray {
+    let ray_dir = get_ray_dir(camera, uv);
+    return Ray(get_ray_origin(camera, uv, ray_dir), ray_dir);
+}
+
+// The angle one unit of screen coordinates covers at the centre of the screen, orthographic cameras cover none
+fn coords_angle(camera: ShaderCamera) -> f32 {
+    switch camera.projection {
+        case 1u: {
+            return 0.;
+        }
+        case 2u: {
+            return camera.fov * 0.5;
+        }
+        case 3u: {
+            return pi * 0.5;
+        }
+        default: {
+            return 1. / focal_length(camera);
+        }
+    }
 }
 
 // fn get_ray_dir_with_fragment_camera(camera: Camera, uv: vec2<f32>) -> vec3<f32> {
@@ -1,21 +1,65 @@
 #define_import_path ray_marching::shapes
 
+#import ray_marching::modifiers::{ShapeDomain, ShapeDisplacement};
+#import ray_marching::noise::{perlin_fbm_2d, fractal_bounding};
+#import ray_marching::materials::ShapeMaterial;
+
 struct Shape {
     shape_type: u32,
+    // Non zero if the shape isn't drawn
+    hidden: u32,
+    // Stays the same when shapes are reordered
+    id: u32,
+    // Non zero if the shape is moved by physics, which replaces its animation
+    dynamic: u32,
     pos: vec3<f32>,
     size: vec3<f32>,
+    // A quaternion, rotating the shape about its position
+    rotation: vec4<f32>,
+    fractal: ShapeFractal,
+    domain: ShapeDomain,
+    displacement: ShapeDisplacement,
+    terrain: ShapeTerrain,
+    plane: ShapePlane,
+    material: ShapeMaterial,
+};
+
+struct ShapeFractal {
+    iterations: u32,
+    power: f32,
+    bailout: f32,
+};
+
+struct ShapeTerrain {
+    octaves: u32,
+    lacunarity: f32,
+    gain: f32,
+    scale: f32,
+    height: f32,
+};
+
+struct ShapePlane {
+    normal: vec3<f32>,
+    offset: f32,
+    thickness: f32,
+    half_extents: vec2<f32>,
+    pattern: u32,
+    pattern_scale: f32,
 };
 
+const no_orbit_trap = vec4<f32>(1.);
+// The Mandelbox's estimate runs a few percent long a couple of units away from it
+const mandelbox_step_scale = 0.9;
+// The steepest each octave of the terrain's noise gets, a little above the largest gradient found by searching the noise
+const perlin_2d_max_gradient = 3.;
+
 struct SDFOutput {
     dist: f32,
     colour: vec3<f32>,
     shape_type: u32,
+    orbit_trap: vec4<f32>,
 };
 
-fn repeat(p: vec3<f32>, c: f32, r: f32) -> vec3<f32> {
-    return fract(p / c) - r / 2. * c;
-}
-
 fn shape_to_sdf(p: vec3<f32>, shape: Shape, union_type: u32, time: f32) -> SDFOutput {
     var infinity: f32;
     if union_type == 0 {
@@ -35,13 +79,75 @@ fn shape_to_sdf(p: vec3<f32>, shape: Shape, union_type: u32, time: f32) -> SDFOu
             return sdf_cube(p, shape.pos, shape.size);
         }
         case(3u) {
-            return sdf_plane(p, shape.pos, shape.size.xy);
+            return sdf_plane(p, shape.pos, shape.plane);
         }
         case (4u) {
             return sdf_portal(p, shape.pos, shape.size);
         }
+        case (5u) {
+            return sdf_mandelbulb(p, shape.pos, shape.size.x, shape.fractal);
+        }
+        case (6u) {
+            return sdf_menger_sponge(p, shape.pos, shape.size.x, shape.fractal);
+        }
+        case (7u) {
+            return sdf_sierpinski_tetrahedron(p, shape.pos, shape.size.x, shape.fractal);
+        }
+        case (8u) {
+            return sdf_mandelbox(p, shape.pos, shape.size.x, shape.fractal);
+        }
+        case (9u) {
+            return sdf_terrain(p, shape.pos, shape.terrain);
+        }
         default {
-            return SDFOutput(infinity, vec3<f32>(0., 0., 0.), 0);
+            return SDFOutput(infinity, vec3<f32>(0., 0., 0.), 0, no_orbit_trap);
+        }
+    }
+}
+
+// How much of a shape's distance it is safe to step, for shapes whose distance is only an estimate
+fn sdf_step_scale(shape: Shape) -> f32 {
+    switch shape.shape_type {
+        case (8u) {
+            return mandelbox_step_scale;
+        }
+        case (9u) {
+            return terrain_step_scale(shape.terrain);
+        }
+        default {
+            return 1.;
+        }
+    }
+}
+
+// How far the shape reaches from its centre, planes and terrain go on forever so are treated as unit sized
+fn bounding_radius(shape: Shape) -> f32 {
+    switch shape.shape_type {
+        case (1u) {
+            return shape.size.x;
+        }
+        case 2u, 4u {
+            return length(shape.size);
+        }
+        case (5u) {
+            return shape.fractal.bailout * shape.size.x;
+        }
+        case 6u, 7u {
+            return sqrt(3.) * shape.size.x;
+        }
+        case (8u) {
+            let power = shape.fractal.power;
+            var half_width = shape.fractal.bailout;
+            if power < -1. {
+                half_width = 2.;
+            } else if power > 1. {
+                half_width = 2. * (power + 1.) / (power - 1.);
+            }
+
+            return sqrt(3.) * half_width * shape.size.x;
+        }
+        default {
+            return 1.;
         }
     }
 }
@@ -49,23 +155,271 @@ fn shape_to_sdf(p: vec3<f32>, shape: Shape, union_type: u32, time: f32) -> SDFOu
 fn sdf_sphere(p: vec3<f32>, centre: vec3<f32>, radius: f32) -> SDFOutput {
     let colour = vec3<f32>(1., 0., 1.);
     
-    return SDFOutput(distance(p, centre) - radius, colour, 1);
+    return SDFOutput(distance(p, centre) - radius, colour, 1, no_orbit_trap);
 }
 
 fn sdf_cube(p: vec3<f32>, centre: vec3<f32>, size: vec3<f32>) -> SDFOutput {
     let colour = vec3<f32>(0., 1., 1.);
 
-    return SDFOutput(length(max(abs(p - centre) - size, vec3<f32>(0.0, 0.0, 0.0))), colour, 2);
+    return SDFOutput(length(max(abs(p - centre) - size, vec3<f32>(0.0, 0.0, 0.0))), colour, 2, no_orbit_trap);
 }
 
-fn sdf_plane(p: vec3<f32>, normal: vec3<f32>, size: vec2<f32>) -> SDFOutput {
+// A plane offset from the centre along its normal, which becomes a slab when it has thickness and a finite quad or box
+// when it has half extents (an extent of zero is infinite)
+fn sdf_plane(p: vec3<f32>, centre: vec3<f32>, plane: ShapePlane) -> SDFOutput {
+    let normal = plane.normal;
+
+    // Coordinates across the plane, and the height above it
+    let helper = select(vec3<f32>(1., 0., 0.), vec3<f32>(0., 0., 1.), abs(normal.x) > 0.9);
+    let tangent = normalize(cross(helper, normal));
+    let bitangent = cross(normal, tangent);
+
+    let q = p - centre;
+    let local = vec3<f32>(dot(q, tangent), dot(q, normal) - plane.offset, dot(q, bitangent));
+
+    // Infinite axes never contribute to the distance
+    let extents = vec3<f32>(plane.half_extents.x, plane.thickness, plane.half_extents.y);
+    let finite = vec3<bool>(plane.half_extents.x > 0., true, plane.half_extents.y > 0.);
+    let d = select(vec3<f32>(-9999.), abs(local) - extents, finite);
+
+    let dist = length(max(d, vec3<f32>(0.))) + min(max(d.x, max(d.y, d.z)), 0.);
+
+    return SDFOutput(dist, plane_colour(local.xz / max(plane.pattern_scale, 1e-6), plane.pattern), 3, no_orbit_trap);
+}
+
+// Procedural textures in the plane's coordinates, where a pattern scale of 1 gives squares one unit wide
+fn plane_colour(uv: vec2<f32>, pattern: u32) -> vec3<f32> {
     let colour = vec3<f32>(0.1, 0.5, 0.1);
 
-    return SDFOutput(abs(dot(p, normal) - size[0]) - size[1], colour, 3);
+    switch pattern {
+        // Checkerboard
+        case (1u) {
+            let cell = floor(uv);
+            let checker = modulo(vec3<f32>(cell.x + cell.y), vec3<f32>(2.)).x;
+
+            return mix(colour, vec3<f32>(0.8), checker);
+        }
+        // Grid lines
+        case (2u) {
+            let line_width = 0.05;
+            let to_line = abs(fract(uv + 0.5) - 0.5);
+            let on_line = min(to_line.x, to_line.y) < line_width;
+
+            return select(colour, vec3<f32>(0.9), on_line);
+        }
+        default {
+            return colour;
+        }
+    }
 }
 
 fn sdf_portal(p: vec3<f32>, centre: vec3<f32>, size: vec3<f32>) -> SDFOutput {
     let colour = vec3<f32>(1., 0., 0.);
 
-    return SDFOutput(length(max(abs(p - centre) - size, vec3<f32>(0.0, 0.0, 0.0))), colour, 4);
+    return SDFOutput(length(max(abs(p - centre) - size, vec3<f32>(0.0, 0.0, 0.0))), colour, 4, no_orbit_trap);
+}
+
+// The scale is the bulb's radius, the distance estimate uses the running derivative of the iteration
+fn sdf_mandelbulb(p: vec3<f32>, centre: vec3<f32>, scale: f32, fractal: ShapeFractal) -> SDFOutput {
+    var c = (p - centre) / scale;
+
+    // Outside the bailout radius the estimate grows faster than the distance does, so estimate from where the point's
+    // direction meets the bailout sphere and add on the distance to it
+    let beyond_bailout = max(length(c) - fractal.bailout, 0.);
+    if beyond_bailout > 0. {
+        c *= fractal.bailout / length(c);
+    }
+
+    var z = c;
+    var dr = 1.;
+    var r = length(z);
+    var trap = vec4<f32>(abs(z), dot(z, z));
+
+    for (var i = 0u; i < fractal.iterations; i++) {
+        r = length(z);
+        if r > fractal.bailout {
+            break;
+        }
+
+        // Raise z to the given power in spherical coordinates
+        let theta = acos(clamp(z.z / max(r, 1e-6), -1., 1.)) * fractal.power;
+        let phi = atan2(z.y, z.x) * fractal.power;
+        dr = pow(r, fractal.power - 1.) * fractal.power * dr + 1.;
+
+        z = pow(r, fractal.power) * vec3<f32>(sin(theta) * cos(phi), sin(phi) * sin(theta), cos(theta)) + c;
+        trap = min(trap, vec4<f32>(abs(z), dot(z, z)));
+    }
+
+    let dist = (0.5 * log(max(r, 1e-6)) * r / dr + beyond_bailout) * scale;
+
+    return SDFOutput(dist, orbit_trap_colour(trap, vec3<f32>(1., 0.6, 0.2)), 5, trap);
+}
+
+// Each iteration carves the middle cross out of every cube, the power is the subdivision scale (3 for a Menger sponge)
+fn sdf_menger_sponge(p: vec3<f32>, centre: vec3<f32>, size: f32, fractal: ShapeFractal) -> SDFOutput {
+    let q = (p - centre) / size;
+    var dist = sdf_box(q, vec3<f32>(1.));
+    var scale = 1.;
+    var trap = vec4<f32>(abs(q), dot(q, q));
+
+    for (var i = 0u; i < fractal.iterations; i++) {
+        let a = modulo(q * scale, vec3<f32>(2.)) - 1.;
+        scale *= fractal.power;
+
+        let r = abs(1. - fractal.power * abs(a));
+        let hole = (min(max(r.x, r.y), min(max(r.y, r.z), max(r.z, r.x))) - 1.) / scale;
+
+        if hole > dist {
+            dist = hole;
+            trap = min(trap, vec4<f32>(abs(a), dot(a, a)));
+        }
+    }
+
+    return SDFOutput(dist * size, orbit_trap_colour(trap, vec3<f32>(0.2, 0.6, 1.)), 6, trap);
+}
+
+// Fold space across the tetrahedron's symmetry planes, the power is the scale of each copy (2 for a Sierpinski tetrahedron)
+fn sdf_sierpinski_tetrahedron(p: vec3<f32>, centre: vec3<f32>, size: f32, fractal: ShapeFractal) -> SDFOutput {
+    var z = (p - centre) / size;
+    var trap = vec4<f32>(abs(z), dot(z, z));
+
+    var i = 0u;
+    for (; i < fractal.iterations; i++) {
+        if dot(z, z) > fractal.bailout * fractal.bailout {
+            break;
+        }
+
+        if z.x + z.y < 0. {
+            z = vec3<f32>(-z.y, -z.x, z.z);
+        }
+        if z.x + z.z < 0. {
+            z = vec3<f32>(-z.z, z.y, -z.x);
+        }
+        if z.y + z.z < 0. {
+            z = vec3<f32>(z.x, -z.z, -z.y);
+        }
+
+        z = z * fractal.power - vec3<f32>(fractal.power - 1.);
+        trap = min(trap, vec4<f32>(abs(z), dot(z, z)));
+    }
+
+    let dist = sdf_tetrahedron(z) * pow(fractal.power, -f32(i));
+
+    return SDFOutput(dist * size, orbit_trap_colour(trap, vec3<f32>(0.9, 0.9, 0.3)), 7, trap);
+}
+
+// Box fold then sphere fold, the power is the scale of each iteration (negative scales give a different fractal)
+fn sdf_mandelbox(p: vec3<f32>, centre: vec3<f32>, size: f32, fractal: ShapeFractal) -> SDFOutput {
+    let min_radius_sq = 0.25;
+    let fixed_radius_sq = 1.;
+
+    let c = (p - centre) / size;
+    var z = c;
+    var dr = 1.;
+    var trap = vec4<f32>(abs(z), dot(z, z));
+
+    for (var i = 0u; i < fractal.iterations; i++) {
+        z = clamp(z, vec3<f32>(-1.), vec3<f32>(1.)) * 2. - z;
+
+        let r_sq = dot(z, z);
+        if r_sq < min_radius_sq {
+            z *= fixed_radius_sq / min_radius_sq;
+            dr *= fixed_radius_sq / min_radius_sq;
+        } else if r_sq < fixed_radius_sq {
+            z *= fixed_radius_sq / r_sq;
+            dr *= fixed_radius_sq / r_sq;
+        }
+
+        z = z * fractal.power + c;
+        dr = dr * abs(fractal.power) + 1.;
+        trap = min(trap, vec4<f32>(abs(z), dot(z, z)));
+
+        if dot(z, z) > fractal.bailout * fractal.bailout {
+            break;
+        }
+    }
+
+    let dist = length(z) / abs(dr);
+
+    return SDFOutput(dist * size, orbit_trap_colour(trap, vec3<f32>(0.6, 0.3, 0.9)), 8, trap);
+}
+
+// The height of the ground above the point, where the centre is the average height of the terrain
+fn terrain_height(p: vec2<f32>, centre: vec3<f32>, terrain: ShapeTerrain) -> f32 {
+    let noise = perlin_fbm_2d((p - centre.xz) / terrain.scale, terrain.octaves, terrain.lacunarity, terrain.gain);
+
+    return centre.y + noise * terrain.height;
+}
+
+// The vertical distance to the heightfield, which overestimates the distance on slopes so needs a smaller step scale
+fn sdf_terrain(p: vec3<f32>, centre: vec3<f32>, terrain: ShapeTerrain) -> SDFOutput {
+    let height = terrain_height(p.xz, centre, terrain);
+    let dist = p.y - height;
+
+    // The colour is only used where a ray hits, so only work out the slope when close to the surface
+    var colour = vec3<f32>(0.);
+    if abs(dist) < 0.1 {
+        let e = 0.01;
+        let dx = terrain_height(p.xz + vec2<f32>(e, 0.), centre, terrain) - height;
+        let dz = terrain_height(p.xz + vec2<f32>(0., e), centre, terrain) - height;
+        let normal = normalize(vec3<f32>(-dx, e, -dz));
+
+        colour = terrain_colour((height - centre.y) / max(terrain.height, 1e-6), normal.y);
+    }
+
+    return SDFOutput(dist, colour, 9, no_orbit_trap);
+}
+
+// Band the colour by height (from -1 to 1) then show rock on steep slopes, where the steepness is the normal's y
+fn terrain_colour(height: f32, steepness: f32) -> vec3<f32> {
+    let sand = vec3<f32>(0.76, 0.7, 0.5);
+    let grass = vec3<f32>(0.25, 0.5, 0.2);
+    let rock = vec3<f32>(0.45, 0.4, 0.38);
+    let snow = vec3<f32>(0.95, 0.95, 1.);
+
+    var colour = mix(sand, grass, smoothstep(-0.45, -0.35, height));
+    colour = mix(colour, rock, smoothstep(0.2, 0.3, height));
+    colour = mix(colour, snow, smoothstep(0.45, 0.55, height));
+
+    return mix(rock, colour, smoothstep(0.6, 0.8, steepness));
+}
+
+// The steepest the heightfield can be, from the gradient each octave of noise can add
+fn terrain_step_scale(terrain: ShapeTerrain) -> f32 {
+    var octave_gradient = 1.;
+    var max_gradient = 0.;
+    for (var i = 0u; i < terrain.octaves; i++) {
+        max_gradient += octave_gradient;
+        octave_gradient *= terrain.gain * terrain.lacunarity;
+    }
+
+    let slope = perlin_2d_max_gradient * max_gradient * fractal_bounding(terrain.octaves, terrain.gain) * terrain.height / terrain.scale;
+
+    return 1. / sqrt(1. + slope * slope);
+}
+
+fn sdf_box(p: vec3<f32>, size: vec3<f32>) -> f32 {
+    let q = abs(p) - size;
+
+    return length(max(q, vec3<f32>(0.))) + min(max(q.x, max(q.y, q.z)), 0.);
+}
+
+// Distance to a regular tetrahedron with vertices at (1, 1, 1), (-1, -1, 1), (1, -1, -1) and (-1, 1, -1)
+fn sdf_tetrahedron(p: vec3<f32>) -> f32 {
+    return (max(max(-p.x - p.y - p.z, p.x + p.y - p.z), max(-p.x + p.y + p.z, p.x - p.y + p.z)) - 1.) / sqrt(3.);
+}
+
+// Colour a fractal by how close its orbit came to the axes and the origin
+fn orbit_trap_colour(trap: vec4<f32>, base_colour: vec3<f32>) -> vec3<f32> {
+    var colour = base_colour * 0.3;
+    colour = mix(colour, base_colour, clamp(sqrt(trap.w), 0., 1.));
+    colour = mix(colour, vec3<f32>(1., 0.9, 0.7), clamp(1. - trap.x * 4., 0., 1.) * 0.5);
+    colour = mix(colour, base_colour.zxy, clamp(1. - trap.y * 4., 0., 1.) * 0.5);
+
+    return clamp(colour, vec3<f32>(0.), vec3<f32>(1.));
+}
+
+// Floored modulo, WGSL's % truncates towards zero which mirrors negative coordinates
+fn modulo(x: vec3<f32>, y: vec3<f32>) -> vec3<f32> {
+    return x - y * floor(x / y);
 }
@@ -0,0 +1,187 @@
+#define_import_path ray_marching::sky
+
+const pi: f32 = 3.14159265;
+
+// Earth-like atmosphere, in metres
+const planet_radius: f32 = 6371e3;
+const atmosphere_radius: f32 = 6471e3;
+const viewer_height: f32 = 1e3;
+const rayleigh_coefficient = vec3<f32>(5.5e-6, 13.0e-6, 22.4e-6);
+const mie_coefficient: f32 = 21e-6;
+const rayleigh_scale_height: f32 = 8e3;
+const mie_scale_height: f32 = 1.2e3;
+// How much Mie scattering is focused around the sun
+const mie_direction: f32 = 0.758;
+const primary_steps = 16;
+const light_steps = 8;
+
+@group(2) @binding(8)
+var<uniform> sky: ShaderSky;
+
+struct ShaderSky {
+    // 0 for a gradient, 1 for atmospheric scattering
+    sky_type: u32,
+    // Points towards the sun, and is the direction of directional lights
+    sun_direction: vec3<f32>,
+    sun_colour: vec3<f32>,
+    sun_intensity: f32,
+    sun_disc: u32,
+    // The angular radius of the sun disc, in radians
+    sun_size: f32,
+    stars: u32,
+    star_density: f32,
+    zenith_colour: vec3<f32>,
+    horizon_colour: vec3<f32>,
+    ground_colour: vec3<f32>,
+};
+
+// The colour of the sky in a direction, as seen in the background
+fn sky_colour(dir: vec3<f32>) -> vec3<f32> {
+    var colour = sky_ambient(dir);
+
+    // Neither the sun nor the stars can be seen through the ground
+    let above_horizon = 1. - smoothstep(0., 0.05, -dir.y);
+
+    if sky.sun_disc != 0u {
+        let cos_angle = dot(dir, sky.sun_direction);
+        let disc = smoothstep(cos(sky.sun_size), cos(sky.sun_size * 0.8), cos_angle);
+        colour += sky.sun_colour * sky.sun_intensity * disc * above_horizon;
+    }
+
+    if sky.stars != 0u {
+        // Stars fade in as the sun sets
+        let night = 1. - smoothstep(-0.1, 0.1, sky.sun_direction.y);
+        colour += vec3<f32>(star_field(dir, sky.star_density) * night * above_horizon);
+    }
+
+    return colour;
+}
+
+// The sky without the sun disc or stars, used as the environment for image-based lighting
+fn sky_ambient(dir: vec3<f32>) -> vec3<f32> {
+    let up_dir = normalize(vec3<f32>(dir.x, max(dir.y, 1e-3), dir.z));
+
+    var colour: vec3<f32>;
+    var ground: vec3<f32>;
+    if sky.sky_type == 1u {
+        colour = atmosphere(up_dir, sky.sun_direction) * sky.sun_colour * sky.sun_intensity;
+        // The ground is lit by the sky at the horizon
+        ground = sky.ground_colour * colour;
+    } else {
+        colour = mix(sky.horizon_colour, sky.zenith_colour, sqrt(up_dir.y));
+        ground = sky.ground_colour;
+    }
+
+    return mix(colour, ground, smoothstep(0., 0.05, -dir.y));
+}
+
+// The colour fog should fade to, the sky at the horizon in the same direction so distant shapes blend into it
+fn sky_fog_colour(dir: vec3<f32>) -> vec3<f32> {
+    var horizontal = vec3<f32>(dir.x, 0., dir.z);
+    if dot(horizontal, horizontal) < 1e-8 {
+        horizontal = vec3<f32>(1., 0., 0.);
+    }
+
+    return sky_ambient(normalize(horizontal));
+}
+
+// Single scattering of sunlight through the atmosphere, by Rayleigh (air) and Mie (haze) scattering
+fn atmosphere(dir: vec3<f32>, sun_dir: vec3<f32>) -> vec3<f32> {
+    let origin = vec3<f32>(0., planet_radius + viewer_height, 0.);
+
+    // Only march through the part of the atmosphere that is above the ground
+    var bounds = ray_sphere(origin, dir, atmosphere_radius);
+    let ground = ray_sphere(origin, dir, planet_radius);
+    if ground.x > 0. {
+        bounds.y = min(bounds.y, ground.x);
+    }
+    bounds.x = max(bounds.x, 0.);
+    if bounds.x > bounds.y {
+        return vec3<f32>(0.);
+    }
+
+    let step_size = (bounds.y - bounds.x) / f32(primary_steps);
+
+    // How much light is scattered towards the viewer, depending on the angle to the sun
+    let mu = dot(dir, sun_dir);
+    let g2 = mie_direction * mie_direction;
+    let phase_rayleigh = 3. / (16. * pi) * (1. + mu * mu);
+    let phase_mie = 3. / (8. * pi) * ((1. - g2) * (1. + mu * mu))
+        / (pow(1. + g2 - 2. * mu * mie_direction, 1.5) * (2. + g2));
+
+    var total_rayleigh = vec3<f32>(0.);
+    var total_mie = vec3<f32>(0.);
+    var optical_depth_rayleigh = 0.;
+    var optical_depth_mie = 0.;
+
+    for (var i = 0; i < primary_steps; i++) {
+        let sample_pos = origin + dir * (bounds.x + (f32(i) + 0.5) * step_size);
+        let height = length(sample_pos) - planet_radius;
+
+        let step_rayleigh = exp(-height / rayleigh_scale_height) * step_size;
+        let step_mie = exp(-height / mie_scale_height) * step_size;
+        optical_depth_rayleigh += step_rayleigh;
+        optical_depth_mie += step_mie;
+
+        // How much of the sunlight reaches this sample
+        let light_step_size = ray_sphere(sample_pos, sun_dir, atmosphere_radius).y / f32(light_steps);
+        var light_depth_rayleigh = 0.;
+        var light_depth_mie = 0.;
+
+        for (var j = 0; j < light_steps; j++) {
+            let light_sample_pos = sample_pos + sun_dir * ((f32(j) + 0.5) * light_step_size);
+            let light_height = length(light_sample_pos) - planet_radius;
+
+            light_depth_rayleigh += exp(-light_height / rayleigh_scale_height) * light_step_size;
+            light_depth_mie += exp(-light_height / mie_scale_height) * light_step_size;
+        }
+
+        let attenuation = exp(-(
+            mie_coefficient * (optical_depth_mie + light_depth_mie)
+                + rayleigh_coefficient * (optical_depth_rayleigh + light_depth_rayleigh)
+        ));
+
+        total_rayleigh += step_rayleigh * attenuation;
+        total_mie += step_mie * attenuation;
+    }
+
+    return phase_rayleigh * rayleigh_coefficient * total_rayleigh + phase_mie * mie_coefficient * total_mie;
+}
+
+// The near and far distances along a ray to where it crosses a sphere at the origin, near is greater than far on a miss
+fn ray_sphere(origin: vec3<f32>, dir: vec3<f32>, radius: f32) -> vec2<f32> {
+    let b = dot(dir, origin);
+    let c = dot(origin, origin) - radius * radius;
+    let discriminant = b * b - c;
+
+    if discriminant < 0. {
+        return vec2<f32>(1e9, -1e9);
+    }
+
+    let root = sqrt(discriminant);
+
+    return vec2<f32>(-b - root, -b + root);
+}
+
+// Stars scattered randomly over a grid on the sky, with density from 0 to 1 being the chance of each cell having one
+fn star_field(dir: vec3<f32>, density: f32) -> f32 {
+    let p = dir * 150.;
+    let cell = floor(p);
+
+    if hash_31(cell) > density * 0.2 {
+        return 0.;
+    }
+
+    let centre = cell + 0.25 + 0.5 * vec3<f32>(hash_31(cell + 17.), hash_31(cell + 31.), hash_31(cell + 47.));
+    let brightness = hash_31(cell + 73.);
+
+    return brightness * (1. - smoothstep(0., 0.15, length(p - centre)));
+}
+
+// Dave Hoskins' hash without sine
+fn hash_31(p: vec3<f32>) -> f32 {
+    var q = fract(p * 0.1031);
+    q += dot(q, q.zyx + 31.32);
+
+    return fract((q.x + q.y) * q.z);
+}
@@ -0,0 +1,28 @@
+#define_import_path ray_marching::stereo
+
+#import ray_marching::ray::{ShaderCamera, Ray, get_ray};
+
+struct ShaderStereo {
+    // 0 for a single view, 1 side by side, 2 red-cyan anaglyph
+    mode: u32,
+    eye_separation: f32,
+    // The distance both eyes' rays meet at
+    convergence: f32,
+    // The right eye goes where the left would, and the other way round
+    swap_eyes: u32,
+};
+
+// The ray from one eye, -1 for the left, 1 for the right and 0 for the camera itself. The eyes sit either side of the
+// camera along its right, and aim at where the camera's own ray is the convergence distance along, so every
+// projection gets the same parallax without toeing the eyes in
+fn get_eye_ray(camera: ShaderCamera, stereo: ShaderStereo, eye: f32, uv: vec2<f32>) -> Ray {
+    let ray = get_ray(camera, uv);
+    if eye == 0. {
+        return ray;
+    }
+
+    let focus = ray.origin + ray.dir * stereo.convergence;
+    let origin = ray.origin + camera.right * eye * stereo.eye_separation * 0.5;
+
+    return Ray(origin, normalize(focus - origin));
+}
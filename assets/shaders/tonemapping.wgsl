@@ -0,0 +1,92 @@
+#define_import_path ray_marching::tonemapping
+
+// Scale the HDR colour by the exposure in stops, then map it into the 0 to 1 range of the display. The result is
+// still linear, the sRGB render target does the encoding
+fn tonemap(colour: vec3<f32>, exposure: f32, tonemapper: u32) -> vec3<f32> {
+    let exposed = max(colour * exp2(exposure), vec3<f32>(0.));
+
+    switch tonemapper {
+        // Reinhard
+        case (1u) {
+            return reinhard(exposed);
+        }
+        // ACES
+        case (2u) {
+            return aces_fitted(exposed);
+        }
+        // AgX
+        case (3u) {
+            return agx(exposed);
+        }
+        // Filmic
+        case (4u) {
+            return filmic(exposed);
+        }
+        // None, anything above 1 is clipped by the display
+        default {
+            return exposed;
+        }
+    }
+}
+
+fn reinhard(colour: vec3<f32>) -> vec3<f32> {
+    return colour / (1. + colour);
+}
+
+// Krzysztof Narkowicz's curve fit of the ACES reference rendering transform
+fn aces_fitted(colour: vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+
+    return clamp((colour * (a * colour + b)) / (colour * (c * colour + d) + e), vec3<f32>(0.), vec3<f32>(1.));
+}
+
+// Troy Sobotka's AgX, using Benjamin Wrensch's polynomial fit of the default contrast curve
+fn agx(colour: vec3<f32>) -> vec3<f32> {
+    let inset = mat3x3<f32>(
+        0.842479062253094, 0.0423282422610123, 0.0423756549057051,
+        0.0784335999999992, 0.878468636469772, 0.0784336,
+        0.0792237451477643, 0.0791661274605434, 0.879142973793104,
+    );
+    let outset = mat3x3<f32>(
+        1.19687900512017, -0.0528968517574562, -0.0529716355144438,
+        -0.0980208811401368, 1.15190312990417, -0.0980434501171241,
+        -0.0990297440797205, -0.0989611768448433, 1.15107367264116,
+    );
+    let min_ev = -12.47393;
+    let max_ev = 4.026069;
+
+    // Log encode the colour into the range the curve expects
+    var encoded = log2(max(inset * colour, vec3<f32>(1e-10)));
+    encoded = (clamp(encoded, vec3<f32>(min_ev), vec3<f32>(max_ev)) - min_ev) / (max_ev - min_ev);
+
+    let x2 = encoded * encoded;
+    let x4 = x2 * x2;
+    let curve = 15.5 * x4 * x2 - 40.14 * x4 * encoded + 31.96 * x4 - 6.868 * x2 * encoded + 0.4298 * x2
+        + 0.1191 * encoded - 0.00232;
+
+    // The curve's output is display encoded, so convert it back to linear
+    return pow(max(outset * curve, vec3<f32>(0.)), vec3<f32>(2.2));
+}
+
+// John Hable's Uncharted 2 curve
+fn filmic(colour: vec3<f32>) -> vec3<f32> {
+    let exposure_bias = 2.;
+    let white_point = 11.2;
+
+    return hable_partial(colour * exposure_bias) / hable_partial(vec3<f32>(white_point));
+}
+
+fn hable_partial(x: vec3<f32>) -> vec3<f32> {
+    let a = 0.15;
+    let b = 0.5;
+    let c = 0.1;
+    let d = 0.2;
+    let e = 0.02;
+    let f = 0.3;
+
+    return ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f;
+}
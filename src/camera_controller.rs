@@ -9,73 +9,158 @@ use bevy::{
     window::{CursorGrabMode, PrimaryWindow},
 };
 use bevy_inspector_egui::{
-    prelude::ReflectInspectorOptions, quick::ResourceInspectorPlugin, InspectorOptions,
+    bevy_egui::EguiContexts, prelude::ReflectInspectorOptions, quick::ResourceInspectorPlugin,
+    InspectorOptions,
 };
 
 use std::f32::consts::PI;
 
-use crate::shader_material::ShaderMat;
+use crate::{
+    fullscreen_shader::FullscreenCover,
+    history::UNDO_KEY,
+    sdf::{get_distance, get_normal, ray_march},
+    shader_material::{ShaderMat, ShaderMatInspector},
+};
 
-pub const CAMERA_MAX_FOV: f32 = 100.;
-pub const CAMERA_MAX_ZOOM_LEVEL: f32 = 4.;
-pub const CAMERA_DEFAULT_ZOOM: f32 = 25.;
+// Vertical field of view in degrees. Fisheye lenses can see behind the camera, but perspective is limited to
+// CAMERA_MAX_PERSPECTIVE_FOV as it stretches towards infinity at 180
+pub const CAMERA_MAX_FOV: f32 = 360.;
+pub const CAMERA_MAX_PERSPECTIVE_FOV: f32 = 170.;
+pub const CAMERA_DEFAULT_FOV: f32 = 90.;
+// Half the height of the screen in world units, for orthographic cameras
+pub const CAMERA_DEFAULT_ORTHO_SIZE: f32 = 5.;
 
 pub const CAMERA_MOVEMENT_SPEED: f32 = 15.;
 pub const CAMERA_SPRINTING_SPEED: f32 = CAMERA_MOVEMENT_SPEED * 2.;
 pub const MOUSE_SENSITIVITY: f32 = 0.00012;
 
+// How close the camera can get to a surface when collision is on
+pub const CAMERA_COLLISION_RADIUS: f32 = 0.5;
+// How many times the camera is pushed out of the shapes each step, more handles corners between shapes better
+const COLLISION_ITERATIONS: u32 = 4;
+// Limits the steps a fast move is split into, each of which are no longer than the collision radius
+const MAX_COLLISION_STEPS: u32 = 16;
+
+// Switches between flying and walking
+pub const CAMERA_MODE_KEY: KeyCode = KeyCode::KeyF;
+pub const WALK_SPEED: f32 = 5.;
+pub const WALK_SPRINTING_SPEED: f32 = WALK_SPEED * 1.8;
+pub const WALK_JUMP_SPEED: f32 = 7.;
+pub const WALK_GRAVITY: f32 = 20.;
+// How far above the ground the camera is
+pub const WALK_EYE_HEIGHT: f32 = 1.7;
+// The tallest ledge that can be walked up without jumping
+pub const WALK_STEP_HEIGHT: f32 = 0.4;
+// Falling this far below where walking started puts the camera back there
+const WALK_FALL_LIMIT: f32 = 100.;
+
+#[allow(clippy::too_many_arguments)]
 pub fn camera_move_using_keyboard(
+    cover: Query<&Handle<ShaderMat>, With<FullscreenCover>>,
     mut shader_mats: ResMut<Assets<ShaderMat>>,
-    // mut inspector_mat: ResMut<ShaderMatInspector>,
+    mut inspector_mat: ResMut<ShaderMatInspector>,
     keys: Res<ButtonInput<KeyCode>>,
     mut mouse_grab_event_writer: EventWriter<MouseGrabEvent>,
     time: Res<Time>,
     mut controller_settings: ResMut<ShaderCameraControllerSettings>,
+    mut walk_state: ResMut<CameraWalkState>,
+    mut egui_contexts: EguiContexts,
 ) {
-    for (_handle, mat) in shader_mats.iter_mut() {
-        // Calculate the directions of motion, this allows for movement independent of where the camera is looking
-        let forward = Vec3::new(mat.camera.forward.x, 0., mat.camera.forward.z);
-        let right = Vec3::new(mat.camera.forward.z, 0., -mat.camera.forward.x);
-        let up = Vec3::Y;
-
-        let mut velocity = Vec3::ZERO;
-
-        // Test just pressed keys
-        for key in keys.get_just_pressed() {
-            // Begin sprinting
-            if key == &KeyCode::ShiftLeft {
-                controller_settings.is_sprinting = true;
-            }
+    // Only the on screen camera is moved, captures render from their own copy of it
+    let Some(mat) = cover
+        .get_single()
+        .ok()
+        .and_then(|handle| shader_mats.get_mut(handle))
+    else {
+        return;
+    };
+
+    // Calculate the directions of motion, this allows for movement independent of where the camera is looking
+    let forward = Vec3::new(mat.camera.forward.x, 0., mat.camera.forward.z);
+    let right = Vec3::new(mat.camera.forward.z, 0., -mat.camera.forward.x);
+    let up = Vec3::Y;
+
+    let mut velocity = Vec3::ZERO;
+
+    // Keys typed into egui, or held to undo and redo, don't move the camera, though keys let go of still stop sprinting.
+    // Left control moves down, so would otherwise move the camera while undoing
+    let undoing =
+        keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) && keys.pressed(UNDO_KEY);
+    let ignore_keys = egui_contexts.ctx_mut().wants_keyboard_input() || undoing;
+
+    // Test just pressed keys
+    for key in keys.get_just_pressed().filter(|_| !ignore_keys) {
+        // Begin sprinting
+        if key == &KeyCode::ShiftLeft {
+            controller_settings.is_sprinting = true;
         }
 
-        // Test pressed keys
-        for key in keys.get_pressed() {
-            match key {
-                // Movement (Modify the velocity in the given camera direction)
-                KeyCode::KeyW => velocity += forward,
-                KeyCode::KeyS => velocity -= forward,
-                KeyCode::KeyD => velocity += right,
-                KeyCode::KeyA => velocity -= right,
-                KeyCode::Space => velocity += up,
-                KeyCode::ControlLeft => velocity -= up,
+        // Switch between flying and walking
+        if key == &CAMERA_MODE_KEY {
+            controller_settings.mode = match controller_settings.mode {
+                CameraMode::Fly => CameraMode::Walk,
+                CameraMode::Walk => CameraMode::Fly,
+            };
+        }
+    }
+
+    // Start walking from wherever the camera is, whether the mode was changed by the key or in the settings
+    if walk_state.mode != controller_settings.mode {
+        *walk_state = CameraWalkState {
+            start: mat.camera.pos,
+            mode: controller_settings.mode,
+            ..default()
+        };
+    }
 
+    // Test pressed keys
+    for key in keys.get_pressed().filter(|_| !ignore_keys) {
+        match key {
+            // Movement (Modify the velocity in the given camera direction)
+            KeyCode::KeyW => velocity += forward,
+            KeyCode::KeyS => velocity -= forward,
+            KeyCode::KeyD => velocity += right,
+            KeyCode::KeyA => velocity -= right,
+            KeyCode::Space => velocity += up,
+            KeyCode::ControlLeft => velocity -= up,
+
+            // Escape from cursor grab
+            KeyCode::Escape => {
                 // Escape from cursor grab
-                KeyCode::Escape => {
-                    // Escape from cursor grab
-                    mouse_grab_event_writer.send(MouseGrabEvent { is_grab: false });
-                }
-                _ => {}
+                mouse_grab_event_writer.send(MouseGrabEvent { is_grab: false });
             }
+            _ => {}
         }
+    }
 
-        // Test just released keys
-        for key in keys.get_just_released() {
-            // Stop sprinting
-            if key == &KeyCode::ShiftLeft {
-                controller_settings.is_sprinting = false;
-            }
+    // Test just released keys
+    for key in keys.get_just_released() {
+        // Stop sprinting
+        if key == &KeyCode::ShiftLeft {
+            controller_settings.is_sprinting = false;
         }
+    }
 
+    mat.camera.pos = if controller_settings.mode == CameraMode::Walk {
+        let speed = if controller_settings.is_sprinting {
+            controller_settings.walk_sprinting_speed
+        } else {
+            controller_settings.walk_speed
+        };
+
+        // Space jumps rather than flying up
+        let displacement = Vec3::new(velocity.x, 0., velocity.z).normalize_or_zero() * speed;
+        let jump = keys.just_pressed(KeyCode::Space) && !ignore_keys;
+
+        walk(
+            mat,
+            displacement,
+            jump,
+            time.delta_seconds(),
+            &controller_settings,
+            &mut walk_state,
+        )
+    } else {
         // Get the speed depending on if the camera is in sprinting mode
         let speed = if controller_settings.is_sprinting {
             controller_settings.sprinting_speed
@@ -86,19 +171,140 @@ pub fn camera_move_using_keyboard(
         let displacement = velocity.normalize_or_zero() * time.delta_seconds() * speed;
 
         // Normalise the velocity and get the displacement, given the time since the last frame, then update the position
-        mat.camera.pos += displacement;
+        if controller_settings.collision {
+            move_with_collision(mat, displacement, controller_settings.collision_radius)
+        } else {
+            mat.camera.pos + displacement
+        }
+    };
+
+    // Keep the inspector camera where the camera is, without it counting as an edit which would copy the inspector
+    // camera back over the one on screen
+    inspector_mat.bypass_change_detection().camera.pos = mat.camera.pos;
+}
+
+// Move the camera using the CPU versions of the shapes' distances, pushing it out along the surface normal whenever it
+// gets closer than the radius so it slides along surfaces rather than going through them
+pub fn move_with_collision(mat: &ShaderMat, displacement: Vec3, radius: f32) -> Vec3 {
+    let shapes = &mat.shapes[..(mat.shapes_len as usize).min(mat.shapes.len())];
+    let distance =
+        |p: Vec3| get_distance(p, shapes, mat.union_type, mat.smoothness_val, mat.time).dist;
+    let normal = |p: Vec3| get_normal(p, shapes, mat.union_type, mat.smoothness_val, mat.time);
+
+    // Split fast moves into steps, so the camera can't skip over thin shapes
+    let radius = radius.max(0.01);
+    let steps = ((displacement.length() / radius).ceil() as u32).clamp(1, MAX_COLLISION_STEPS);
+    let step = displacement / steps as f32;
+
+    let mut pos = mat.camera.pos;
+    for _ in 0..steps {
+        pos += step;
+
+        for _ in 0..COLLISION_ITERATIONS {
+            let dist = distance(pos);
+            if dist >= radius {
+                break;
+            }
+
+            pos += normal(pos) * (radius - dist);
+        }
+    }
+
+    pos
+}
+
+// Move the camera along the ground, which is found by marching a ray down from a step's height above the feet. Ground
+// higher than a step is treated as a wall, and ground a step below is snapped down to so walking down stairs doesn't
+// bounce
+pub fn walk(
+    mat: &ShaderMat,
+    velocity: Vec3,
+    jump: bool,
+    delta_seconds: f32,
+    settings: &ShaderCameraControllerSettings,
+    state: &mut CameraWalkState,
+) -> Vec3 {
+    let shapes = &mat.shapes[..(mat.shapes_len as usize).min(mat.shapes.len())];
+    let ground_height = |feet: Vec3| {
+        let origin = feet + Vec3::Y * settings.step_height;
+
+        ray_march(
+            origin,
+            Vec3::NEG_Y,
+            shapes,
+            mat.union_type,
+            mat.smoothness_val,
+            mat.time,
+        )
+        .map(|(dist, _)| origin.y - dist)
+    };
+
+    let eye_offset = Vec3::Y * settings.eye_height;
+    let mut pos = mat.camera.pos;
+
+    // Move across the ground, unless the ground ahead is too high to step on to
+    let horizontal = velocity * delta_seconds;
+    let moved = if settings.collision {
+        move_with_collision(mat, horizontal, settings.collision_radius)
+    } else {
+        pos + horizontal
+    };
+    let blocked = ground_height(moved - eye_offset)
+        .is_some_and(|ground| ground >= moved.y - settings.eye_height + settings.step_height);
+    if !blocked {
+        pos = Vec3::new(moved.x, pos.y, moved.z);
+    }
 
-        // TODO fix this so the inspector can see these values
-        // Pointless because this alters the ShaderMat camera as well
-        // move_camera_inspector(&mut inspector_mat.camera, displacement);
+    if jump && state.grounded {
+        state.vertical_velocity = settings.jump_speed;
+    }
+    state.vertical_velocity -= settings.gravity * delta_seconds;
+
+    // Split fast falls into steps no longer than a step's height, so the ground below can't be fallen through
+    let fall = state.vertical_velocity * delta_seconds;
+    let step_height = settings.step_height.max(0.01);
+    let steps = ((fall.abs() / step_height).ceil() as u32).clamp(1, MAX_COLLISION_STEPS);
+
+    // Land on the ground, or follow it down if it is only a step below
+    let snap_distance = if state.grounded && state.vertical_velocity <= 0. {
+        settings.step_height
+    } else {
+        0.
+    };
+    state.grounded = false;
+    for _ in 0..steps {
+        pos.y += fall / steps as f32;
+
+        let feet = pos.y - settings.eye_height;
+        let ground =
+            ground_height(pos - eye_offset).filter(|&ground| feet <= ground + snap_distance);
+        if let Some(ground) = ground.filter(|_| state.vertical_velocity <= 0.) {
+            pos.y = ground + settings.eye_height;
+            state.vertical_velocity = 0.;
+            state.grounded = true;
+            break;
+        }
     }
+
+    if pos.y < state.start.y - WALK_FALL_LIMIT {
+        *state = CameraWalkState {
+            start: state.start,
+            mode: state.mode,
+            ..default()
+        };
+        pos = state.start;
+    }
+
+    pos
 }
 
 pub fn camera_rotate_using_mouse(
     window: Query<&Window, Changed<Window>>,
     mut motion_reader: ResMut<MouseMotionReader>,
     mouse_motion: Res<Events<MouseMotion>>,
+    cover: Query<&Handle<ShaderMat>, With<FullscreenCover>>,
     mut shader_mats: ResMut<Assets<ShaderMat>>,
+    mut inspector_mat: ResMut<ShaderMatInspector>,
     controller_settings: Res<ShaderCameraControllerSettings>,
 ) {
     // Exit the function if the window doesn't exist, or is not grabbing the cursor
@@ -111,27 +317,36 @@ pub fn camera_rotate_using_mouse(
         return;
     }
 
+    // Only the on screen camera is turned, captures render from their own copy of it
+    let Some(mat) = cover
+        .get_single()
+        .ok()
+        .and_then(|handle| shader_mats.get_mut(handle))
+    else {
+        return;
+    };
+
     for event in motion_reader.motion.read(&mouse_motion) {
-        for (_handle, mat) in shader_mats.iter_mut() {
-            // Get the current rotation angles, to be updated
-            let (mut yaw, mut pitch, _) =
-                Quat::from_vec4(mat.camera.rotation).to_euler(EulerRot::YXZ);
+        // Get the current rotation angles, to be updated
+        let (mut yaw, mut pitch, _) = Quat::from_vec4(mat.camera.rotation).to_euler(EulerRot::YXZ);
 
-            // Using smallest of width or height ensures equal vertical and horizontal sensitivity
-            let window_scale = window.height().min(window.width());
-            pitch += (controller_settings.sensitivity * event.delta.y * window_scale).to_radians();
-            yaw += (controller_settings.sensitivity * event.delta.x * window_scale).to_radians();
+        // Using smallest of width or height ensures equal vertical and horizontal sensitivity
+        let window_scale = window.height().min(window.width());
+        pitch += (controller_settings.sensitivity * event.delta.y * window_scale).to_radians();
+        yaw += (controller_settings.sensitivity * event.delta.x * window_scale).to_radians();
 
-            // Clamp pitch to prevent gimbal lock
-            pitch = pitch.clamp(-PI / 2.01, PI / 2.01);
+        // Clamp pitch to prevent gimbal lock
+        pitch = pitch.clamp(-PI / 2.01, PI / 2.01);
 
-            // Creating a rotation quaternion from the new euler angles
-            let rotation = (Quat::from_axis_angle(Vec3::Y, yaw)
-                * Quat::from_axis_angle(Vec3::X, pitch))
-            .normalize();
+        // Creating a rotation quaternion from the new euler angles
+        let rotation = (Quat::from_axis_angle(Vec3::Y, yaw)
+            * Quat::from_axis_angle(Vec3::X, pitch))
+        .normalize();
 
-            rotate_camera(&mut mat.camera, rotation);
-        }
+        rotate_camera(&mut mat.camera, rotation);
+
+        // Like moving, this isn't an edit to the inspector camera
+        inspector_mat.bypass_change_detection().camera.rotation = rotation;
     }
 }
 
@@ -170,6 +385,7 @@ pub struct ShaderCameraControllerPlugin;
 impl Plugin for ShaderCameraControllerPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(ShaderCameraControllerSettings::default())
+            .insert_resource(CameraWalkState::default())
             .init_resource::<MouseMotionReader>()
             .add_plugins(ResourceInspectorPlugin::<ShaderCameraControllerSettings>::default())
             .add_systems(Startup, camera_setup)
@@ -221,41 +437,127 @@ pub fn handle_mouse_button_events(
     }
 }
 
+#[derive(Debug, Copy, Clone, Default, Reflect, PartialEq, Eq)]
+pub enum CameraMode {
+    #[default]
+    Fly,
+    // Falls under gravity and walks along the tops of shapes
+    Walk,
+}
+
+// Where the walking camera is falling, and where it started walking
+#[derive(Debug, Default, Resource)]
+pub struct CameraWalkState {
+    pub vertical_velocity: f32,
+    pub grounded: bool,
+    pub start: Vec3,
+    // The mode this was set up for, so it's set up again when the mode changes
+    pub mode: CameraMode,
+}
+
 #[derive(Resource, Reflect)]
 pub struct ShaderCameraControllerSettings {
+    pub mode: CameraMode,
     pub speed: f32,
     pub sprinting_speed: f32,
     pub sensitivity: f32,
     pub is_sprinting: bool,
+    // Stop the camera going inside shapes
+    pub collision: bool,
+    pub collision_radius: f32,
+    pub walk_speed: f32,
+    pub walk_sprinting_speed: f32,
+    pub jump_speed: f32,
+    pub gravity: f32,
+    pub eye_height: f32,
+    pub step_height: f32,
 }
 
 impl Default for ShaderCameraControllerSettings {
     fn default() -> Self {
         Self {
+            mode: CameraMode::Fly,
             speed: CAMERA_MOVEMENT_SPEED,
             sprinting_speed: CAMERA_SPRINTING_SPEED,
             sensitivity: MOUSE_SENSITIVITY,
             is_sprinting: false,
+            collision: false,
+            collision_radius: CAMERA_COLLISION_RADIUS,
+            walk_speed: WALK_SPEED,
+            walk_sprinting_speed: WALK_SPRINTING_SPEED,
+            jump_speed: WALK_JUMP_SPEED,
+            gravity: WALK_GRAVITY,
+            eye_height: WALK_EYE_HEIGHT,
+            step_height: WALK_STEP_HEIGHT,
+        }
+    }
+}
+
+// How rays leave the camera, see get_ray_dir
+#[derive(Debug, Copy, Clone, Default, Reflect, PartialEq, Eq)]
+pub enum CameraProjection {
+    #[default]
+    Perspective,
+    // Parallel rays, so things don't get smaller with distance
+    Orthographic,
+    // Equidistant, the angle from the centre of the screen grows evenly with the distance from it
+    Fisheye,
+    // Longitude across and latitude up the screen, which sees all the way around at a 2:1 aspect ratio
+    Equirectangular,
+}
+
+impl From<CameraProjection> for u32 {
+    fn from(projection: CameraProjection) -> Self {
+        match projection {
+            CameraProjection::Perspective => 0,
+            CameraProjection::Orthographic => 1,
+            CameraProjection::Fisheye => 2,
+            CameraProjection::Equirectangular => 3,
         }
     }
 }
 
+impl From<u32> for CameraProjection {
+    fn from(projection: u32) -> Self {
+        match projection {
+            1 => Self::Orthographic,
+            2 => Self::Fisheye,
+            3 => Self::Equirectangular,
+            _ => Self::Perspective,
+        }
+    }
+}
+
+// The settings are packed into the gaps after the vectors, matching ray.wgsl
 #[derive(Debug, AsBindGroup, Clone, Asset, TypePath, ShaderType, Default)]
 pub struct ShaderCamera {
     pub pos: Vec3,
-    pub zoom: f32,
+    // Vertical field of view in radians
+    pub fov: f32,
     pub rotation: Vec4,
     pub forward: Vec3,
+    // How far from the camera rays start, anything closer is cut away
+    pub near: f32,
     pub right: Vec3,
+    pub ortho_size: f32,
     pub up: Vec3,
+    pub projection: u32,
 }
 
-#[derive(Debug, Copy, Clone, Asset, Reflect, Resource, InspectorOptions, Component, Default)]
+#[derive(
+    Debug, Copy, Clone, PartialEq, Asset, Reflect, Resource, InspectorOptions, Component, Default,
+)]
 #[reflect(InspectorOptions)]
 pub struct ShaderCameraInspector {
     pub pos: Vec3,
-    #[inspector(min=0., max=CAMERA_MAX_FOV)]
-    pub zoom: f32,
+    pub projection: CameraProjection,
+    // Vertical field of view in degrees, for every projection but orthographic
+    #[inspector(min = 1., max = CAMERA_MAX_FOV)]
+    pub fov: f32,
+    #[inspector(min = 0.)]
+    pub near: f32,
+    #[inspector(min = 0.01)]
+    pub ortho_size: f32,
     pub rotation: Quat,
 }
 
@@ -270,12 +572,20 @@ pub struct MouseMotionReader {
 }
 
 impl ShaderCamera {
+    // The distance to the screen for perspective rays, where it spans -1 to 1 along its shorter axis
+    pub fn focal_length(&self) -> f32 {
+        1. / (self.fov.min(CAMERA_MAX_PERSPECTIVE_FOV.to_radians()) * 0.5).tan()
+    }
+
     // Update the ShaderCamera using the inspector camera
     pub fn modify(&mut self, inspector_cam: ShaderCameraInspector) {
         let (forward, right, up) = get_camera_axes(self.pos, inspector_cam.rotation);
 
         self.pos = inspector_cam.pos;
-        self.zoom = inspector_cam.zoom * CAMERA_MAX_ZOOM_LEVEL / CAMERA_MAX_FOV;
+        self.fov = inspector_cam.fov.to_radians();
+        self.near = inspector_cam.near;
+        self.ortho_size = inspector_cam.ortho_size;
+        self.projection = inspector_cam.projection.into();
         self.rotation = inspector_cam.rotation.into();
 
         self.forward = forward;
@@ -288,7 +598,10 @@ impl From<ShaderCamera> for ShaderCameraInspector {
     fn from(shader_camera: ShaderCamera) -> Self {
         Self {
             pos: shader_camera.pos,
-            zoom: shader_camera.zoom, //  * CAMERA_MAX_FOV / CAMERA_MAX_ZOOM_LEVEL
+            projection: shader_camera.projection.into(),
+            fov: shader_camera.fov.to_degrees(),
+            near: shader_camera.near,
+            ortho_size: shader_camera.ortho_size,
             rotation: Quat::from_vec4(shader_camera.rotation),
         }
     }
@@ -0,0 +1,738 @@
+use bevy::{
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_asset::{RenderAssetUsages, RenderAssets},
+        render_graph::{self, NodeRunError, RenderGraph, RenderGraphContext, RenderLabel},
+        render_resource::{
+            Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Extent3d,
+            ImageCopyBuffer, ImageDataLayout, Maintain, MapMode, PipelineCache, TextureDimension,
+            TextureFormat, TextureUsages,
+        },
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        texture::{BevyDefault, GpuImage, TextureFormatPixelInfo},
+        view::RenderLayers,
+        Extract, Render, RenderApp, RenderSet,
+    },
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+    tasks::IoTaskPool,
+};
+use bevy_inspector_egui::{
+    prelude::ReflectInspectorOptions, quick::ResourceInspectorPlugin, InspectorOptions,
+};
+use crossbeam_channel::{Receiver, Sender};
+use std::{
+    f32::consts::FRAC_PI_2,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    camera_controller::{CameraProjection, ShaderCamera},
+    fullscreen_shader::FullscreenCover,
+    shader_material::{update_time, ShaderMat},
+    tonemapping::Tonemapper,
+};
+
+pub const CAPTURE_RENDER_LAYER: usize = 1;
+pub const CAPTURE_DEFAULT_WIDTH: u32 = 7680;
+pub const CAPTURE_DEFAULT_HEIGHT: u32 = 4320;
+pub const CAPTURE_DEFAULT_TILE_SIZE: u32 = 2048;
+pub const CAPTURE_DEFAULT_FPS: f32 = 30.;
+pub const CAPTURE_DEFAULT_FRAMES: u32 = 120;
+pub const CAPTURE_DEFAULT_DIRECTORY: &str = "captures";
+
+// The directions each cubemap face looks in, with its right and up, in the usual +X, -X, +Y, -Y, +Z, -Z order
+const CUBEMAP_FACES: [(Vec3, Vec3, Vec3); 6] = [
+    (Vec3::X, Vec3::NEG_Z, Vec3::Y),
+    (Vec3::NEG_X, Vec3::Z, Vec3::Y),
+    (Vec3::Y, Vec3::X, Vec3::NEG_Z),
+    (Vec3::NEG_Y, Vec3::X, Vec3::Z),
+    (Vec3::Z, Vec3::X, Vec3::Y),
+    (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y),
+];
+
+pub const SCREENSHOT_KEY: KeyCode = KeyCode::F12;
+pub const SEQUENCE_KEY: KeyCode = KeyCode::F10;
+
+pub struct CapturePlugin;
+
+impl Plugin for CapturePlugin {
+    fn build(&self, app: &mut App) {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+
+        app.insert_resource(CaptureSettings::default())
+            .init_resource::<CaptureState>()
+            .insert_resource(MainWorldReceiver(receiver))
+            .register_type::<CaptureSettings>()
+            .add_plugins(ResourceInspectorPlugin::<CaptureSettings>::default())
+            .add_systems(
+                Update,
+                (start_capture_using_keyboard, drive_capture)
+                    .chain()
+                    .after(update_time),
+            );
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        let mut graph = render_app.world_mut().resource_mut::<RenderGraph>();
+        graph.add_node(ImageCopy, ImageCopyDriver);
+        graph.add_node_edge(bevy::render::graph::CameraDriverLabel, ImageCopy);
+
+        render_app
+            .insert_resource(RenderWorldSender(sender))
+            .add_systems(ExtractSchedule, image_copy_extract)
+            // The copy is only readable once the render graph has been submitted
+            .add_systems(Render, receive_image_from_buffer.after(RenderSet::Render));
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, Reflect, PartialEq, Eq)]
+pub enum CaptureFormat {
+    #[default]
+    Png,
+    Exr,
+}
+
+impl CaptureFormat {
+    // PNGs are stored exactly as they appear on screen, EXRs keep the full floating point range
+    pub fn texture_format(self) -> TextureFormat {
+        match self {
+            CaptureFormat::Png => TextureFormat::bevy_default(),
+            CaptureFormat::Exr => TextureFormat::Rgba32Float,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            CaptureFormat::Png => "png",
+            CaptureFormat::Exr => "exr",
+        }
+    }
+}
+
+// Panoramas are rendered all the way around the camera's position, lined up with the world's axes rather than the
+// camera's rotation, and are as wide as the capture's width with their height following from the layout
+#[derive(Debug, Copy, Clone, Default, Reflect, PartialEq, Eq)]
+pub enum CapturePanorama {
+    // The camera's own view
+    #[default]
+    None,
+    // Twice as wide as it is tall, centred on +Z
+    Equirectangular,
+    // Six square faces side by side
+    Cubemap,
+}
+
+impl CapturePanorama {
+    // The size of the image, the size of each view in it, and the cameras rendering the views
+    fn views(
+        self,
+        camera: &ShaderCamera,
+        width: u32,
+        height: u32,
+    ) -> (UVec2, UVec2, Vec<CaptureView>) {
+        let aligned_camera =
+            |projection: CameraProjection, (forward, right, up): (Vec3, Vec3, Vec3)| ShaderCamera {
+                projection: projection.into(),
+                fov: FRAC_PI_2,
+                rotation: Quat::from_mat3(&Mat3::from_cols(right, up, forward)).into(),
+                forward,
+                right,
+                up,
+                ..camera.clone()
+            };
+
+        match self {
+            CapturePanorama::None => {
+                let size = UVec2::new(width, height);
+                let view = CaptureView {
+                    camera: camera.clone(),
+                    offset: UVec2::ZERO,
+                };
+
+                (size, size, vec![view])
+            }
+            CapturePanorama::Equirectangular => {
+                let size = UVec2::new(width, (width / 2).max(1));
+                let view = CaptureView {
+                    camera: aligned_camera(CameraProjection::Equirectangular, CUBEMAP_FACES[4]),
+                    offset: UVec2::ZERO,
+                };
+
+                (size, size, vec![view])
+            }
+            CapturePanorama::Cubemap => {
+                let face_size = UVec2::splat((width / 6).max(1));
+                let views = CUBEMAP_FACES
+                    .iter()
+                    .enumerate()
+                    .map(|(i, face)| CaptureView {
+                        camera: aligned_camera(CameraProjection::Perspective, *face),
+                        offset: UVec2::new(i as u32 * face_size.x, 0),
+                    })
+                    .collect();
+
+                (face_size * UVec2::new(6, 1), face_size, views)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Resource, Reflect, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
+pub struct CaptureSettings {
+    #[inspector(min = 1)]
+    pub width: u32,
+    #[inspector(min = 1)]
+    pub height: u32,
+    pub format: CaptureFormat,
+    pub panorama: CapturePanorama,
+    // Captures larger than this are rendered in several tiles and stitched together
+    #[inspector(min = 64)]
+    pub max_tile_size: u32,
+    #[inspector(min = 1.)]
+    pub sequence_fps: f32,
+    #[inspector(min = 1)]
+    pub sequence_frames: u32,
+    pub sequence_start_time: f32,
+    pub output_directory: String,
+}
+
+impl Default for CaptureSettings {
+    fn default() -> Self {
+        Self {
+            width: CAPTURE_DEFAULT_WIDTH,
+            height: CAPTURE_DEFAULT_HEIGHT,
+            format: CaptureFormat::default(),
+            panorama: CapturePanorama::default(),
+            max_tile_size: CAPTURE_DEFAULT_TILE_SIZE,
+            sequence_fps: CAPTURE_DEFAULT_FPS,
+            sequence_frames: CAPTURE_DEFAULT_FRAMES,
+            sequence_start_time: 0.,
+            output_directory: CAPTURE_DEFAULT_DIRECTORY.into(),
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct CaptureState {
+    job: Option<CaptureJob>,
+}
+
+impl CaptureState {
+    pub fn is_capturing(&self) -> bool {
+        self.job.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CaptureKind {
+    Screenshot,
+    Sequence { frame: u32, frames: u32, fps: f32 },
+}
+
+// A camera rendering part of the captured image, panoramas have one for each direction they look in
+struct CaptureView {
+    camera: ShaderCamera,
+    offset: UVec2,
+}
+
+// An in-progress capture, rendering one tile of a view per request
+struct CaptureJob {
+    kind: CaptureKind,
+    format: CaptureFormat,
+    size: UVec2,
+    view_size: UVec2,
+    views: Vec<CaptureView>,
+    view: usize,
+    tile_size: UVec2,
+    tile: u32,
+    request: u64,
+    start_time: f32,
+    pixels: Vec<u8>,
+    path: PathBuf,
+    material: Handle<ShaderMat>,
+    entities: Vec<Entity>,
+}
+
+impl CaptureJob {
+    fn tiles(&self) -> UVec2 {
+        (self.view_size + self.tile_size - UVec2::ONE) / self.tile_size
+    }
+
+    fn tile_count(&self) -> u32 {
+        let tiles = self.tiles();
+
+        tiles.x * tiles.y
+    }
+
+    fn tile_offset(&self) -> UVec2 {
+        let tiles_x = self.tiles().x;
+
+        UVec2::new(self.tile % tiles_x, self.tile / tiles_x) * self.tile_size
+    }
+
+    // The shader time of the frame being captured, stepped at a fixed rate for sequences
+    fn time(&self) -> f32 {
+        match self.kind {
+            CaptureKind::Screenshot => self.start_time,
+            CaptureKind::Sequence { frame, fps, .. } => self.start_time + frame as f32 / fps,
+        }
+    }
+
+    fn file_path(&self) -> PathBuf {
+        match self.kind {
+            CaptureKind::Screenshot => self.path.with_extension(self.format.extension()),
+            CaptureKind::Sequence { frame, .. } => self
+                .path
+                .join(format!("frame_{frame:05}"))
+                .with_extension(self.format.extension()),
+        }
+    }
+
+    // Copy a rendered tile (with rows padded to the copy alignment) into the full image
+    fn write_tile(&mut self, data: &[u8]) {
+        let pixel_size = self.format.texture_format().pixel_size();
+        let padded_row_bytes =
+            RenderDevice::align_copy_bytes_per_row(self.tile_size.x as usize * pixel_size);
+
+        let tile_offset = self.tile_offset();
+        let visible = (self.view_size - tile_offset).min(self.tile_size);
+        let offset = self.views[self.view].offset + tile_offset;
+        let row_bytes = visible.x as usize * pixel_size;
+
+        for row in 0..visible.y as usize {
+            let src = row * padded_row_bytes;
+            let dst =
+                ((offset.y as usize + row) * self.size.x as usize + offset.x as usize) * pixel_size;
+
+            self.pixels[dst..dst + row_bytes].copy_from_slice(&data[src..src + row_bytes]);
+        }
+    }
+
+    // Save the stitched image on the IO task pool, so large images don't stall the app
+    fn save(&self) {
+        let path = self.file_path();
+        let (width, height) = (self.size.x, self.size.y);
+        let format = self.format;
+        let pixels = self.pixels.clone();
+
+        IoTaskPool::get()
+            .spawn(async move {
+                if let Some(parent) = path.parent() {
+                    if let Err(e) = std::fs::create_dir_all(parent) {
+                        error!("Failed to create capture directory {parent:?}: {e}");
+                        return;
+                    }
+                }
+
+                let result = match format {
+                    CaptureFormat::Png => image::RgbaImage::from_raw(width, height, pixels)
+                        .map(|image| image.save(&path)),
+                    CaptureFormat::Exr => image::Rgba32FImage::from_raw(
+                        width,
+                        height,
+                        pixels
+                            .chunks_exact(4)
+                            .map(|bytes| {
+                                f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+                            })
+                            .collect(),
+                    )
+                    .map(|image| image.save(&path)),
+                };
+
+                match result {
+                    Some(Ok(())) => info!("Saved capture to {path:?}"),
+                    Some(Err(e)) => error!("Failed to save capture to {path:?}: {e}"),
+                    None => error!("Captured image data did not match its size"),
+                }
+            })
+            .detach();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn start_capture_using_keyboard(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    settings: Res<CaptureSettings>,
+    mut capture_state: ResMut<CaptureState>,
+    mut shader_mats: ResMut<Assets<ShaderMat>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut images: ResMut<Assets<Image>>,
+    render_device: Res<RenderDevice>,
+    cover: Query<&Handle<ShaderMat>, With<FullscreenCover>>,
+) {
+    // Pressing the sequence key again during a sequence stops it early
+    if let Some(job) = capture_state.job.as_mut() {
+        if keys.just_pressed(SEQUENCE_KEY) {
+            info!("Stopped capture early");
+            for entity in job.entities.drain(..) {
+                commands.entity(entity).despawn();
+            }
+            shader_mats.remove(&job.material);
+            capture_state.job = None;
+        }
+        return;
+    }
+
+    let kind = if keys.just_pressed(SCREENSHOT_KEY) {
+        CaptureKind::Screenshot
+    } else if keys.just_pressed(SEQUENCE_KEY) {
+        CaptureKind::Sequence {
+            frame: 0,
+            frames: settings.sequence_frames.max(1),
+            fps: settings.sequence_fps.max(1.),
+        }
+    } else {
+        return;
+    };
+
+    // Capture using a copy of the material currently on screen
+    let Some(mut material) = cover
+        .get_single()
+        .ok()
+        .and_then(|handle| shader_mats.get(handle))
+        .cloned()
+    else {
+        return;
+    };
+
+    let format = settings.format;
+    let (size, view_size, views) = settings.panorama.views(
+        &material.camera,
+        settings.width.max(1),
+        settings.height.max(1),
+    );
+    let max_tile_size = settings
+        .max_tile_size
+        .clamp(64, render_device.limits().max_texture_dimension_2d);
+    let tile_size = view_size.min(UVec2::splat(max_tile_size));
+
+    // The texture that will be rendered to, a tile at a time
+    let extent = Extent3d {
+        width: tile_size.x,
+        height: tile_size.y,
+        ..default()
+    };
+    let texture_format = format.texture_format();
+    let mut target = Image::new_fill(
+        extent,
+        TextureDimension::D2,
+        &vec![0; texture_format.pixel_size()],
+        texture_format,
+        RenderAssetUsages::default(),
+    );
+    target.texture_descriptor.usage |=
+        TextureUsages::COPY_SRC | TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING;
+    let target = images.add(target);
+
+    // Screenshots freeze the current moment, sequences start from the configured time
+    let start_time = match kind {
+        CaptureKind::Screenshot => material.time,
+        CaptureKind::Sequence { .. } => settings.sequence_start_time,
+    };
+
+    // Captures shouldn't show what is under the cursor
+    material.hovered_shape = 0;
+    material.selected_shape = 0;
+
+    // EXRs keep the scene's linear HDR values, only scaled by the exposure, so they can be graded later
+    if format == CaptureFormat::Exr {
+        material.tonemapper = Tonemapper::None.into();
+    }
+
+    let material = shader_mats.add(material);
+    let capture_layer = RenderLayers::layer(CAPTURE_RENDER_LAYER);
+
+    let camera = commands
+        .spawn((
+            Camera2dBundle {
+                camera: Camera {
+                    target: RenderTarget::Image(target.clone()),
+                    // Render before the window, and keep the full floating point range for EXRs
+                    order: -1,
+                    hdr: format == CaptureFormat::Exr,
+                    ..default()
+                },
+                ..default()
+            },
+            capture_layer.clone(),
+        ))
+        .id();
+
+    let cover = commands
+        .spawn((
+            MaterialMesh2dBundle {
+                mesh: Mesh2dHandle::from(
+                    meshes.add(Rectangle::new(tile_size.x as f32, tile_size.y as f32)),
+                ),
+                material: material.clone(),
+                ..default()
+            },
+            capture_layer,
+        ))
+        .id();
+
+    let copier = commands
+        .spawn(ImageCopier::new(
+            target,
+            extent,
+            texture_format,
+            &render_device,
+        ))
+        .id();
+
+    // Name captures by the time they were taken so they never overwrite each other
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let name = match kind {
+        CaptureKind::Screenshot => format!("screenshot_{timestamp}"),
+        CaptureKind::Sequence { .. } => format!("sequence_{timestamp}"),
+    };
+
+    info!(
+        "Capturing {}x{} {:?} {:?} panorama to {name}",
+        size.x, size.y, format, settings.panorama
+    );
+
+    capture_state.job = Some(CaptureJob {
+        kind,
+        format,
+        size,
+        view_size,
+        views,
+        view: 0,
+        tile_size,
+        tile: 0,
+        request: 0,
+        start_time,
+        pixels: vec![0; (size.x * size.y) as usize * texture_format.pixel_size()],
+        path: PathBuf::from(&settings.output_directory).join(name),
+        material,
+        entities: vec![camera, cover, copier],
+    });
+}
+
+fn drive_capture(
+    mut commands: Commands,
+    mut capture_state: ResMut<CaptureState>,
+    receiver: Res<MainWorldReceiver>,
+    mut shader_mats: ResMut<Assets<ShaderMat>>,
+    mut copiers: Query<&mut ImageCopier>,
+) {
+    let Some(job) = capture_state.job.as_mut() else {
+        // Clear out any frames which arrived after a capture was stopped
+        while receiver.try_recv().is_ok() {}
+        return;
+    };
+
+    // Only accept the frame rendered for the current request, older frames may still be arriving
+    let mut tile_finished = false;
+    while let Ok(captured) = receiver.try_recv() {
+        if captured.request == job.request && !tile_finished {
+            job.write_tile(&captured.data);
+            tile_finished = true;
+        }
+    }
+
+    if tile_finished {
+        job.tile += 1;
+        job.request += 1;
+
+        if job.tile == job.tile_count() {
+            job.tile = 0;
+            job.view += 1;
+        }
+
+        if job.view == job.views.len() {
+            job.save();
+            job.view = 0;
+
+            let finished = match &mut job.kind {
+                CaptureKind::Screenshot => true,
+                CaptureKind::Sequence { frame, frames, .. } => {
+                    *frame += 1;
+                    *frame == *frames
+                }
+            };
+
+            if finished {
+                for entity in job.entities.drain(..) {
+                    commands.entity(entity).despawn();
+                }
+                shader_mats.remove(&job.material);
+                capture_state.job = None;
+                return;
+            }
+        }
+    }
+
+    // Point the capture material at the view, tile and time being rendered
+    let (offset, size, time, request) = (job.tile_offset(), job.view_size, job.time(), job.request);
+
+    if let Some(mat) = shader_mats.get_mut(&job.material) {
+        mat.camera = job.views[job.view].camera.clone();
+        mat.viewport_offset = offset.as_vec2();
+        mat.viewport_size = size.as_vec2();
+        mat.time = time;
+    }
+
+    for mut copier in copiers.iter_mut() {
+        copier.request = request;
+    }
+}
+
+/// Receives captured frames from the render world
+#[derive(Resource, Deref)]
+struct MainWorldReceiver(Receiver<CapturedImage>);
+
+/// Sends captured frames to the main world
+#[derive(Resource, Deref)]
+struct RenderWorldSender(Sender<CapturedImage>);
+
+struct CapturedImage {
+    request: u64,
+    data: Vec<u8>,
+}
+
+/// Copies the capture render target into a buffer which can be read on the CPU
+#[derive(Clone, Component)]
+struct ImageCopier {
+    buffer: Buffer,
+    src_image: Handle<Image>,
+    request: u64,
+}
+
+impl ImageCopier {
+    fn new(
+        src_image: Handle<Image>,
+        size: Extent3d,
+        format: TextureFormat,
+        render_device: &RenderDevice,
+    ) -> Self {
+        let padded_bytes_per_row =
+            RenderDevice::align_copy_bytes_per_row(size.width as usize * format.pixel_size());
+
+        let buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("capture_buffer"),
+            size: padded_bytes_per_row as u64 * size.height as u64,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            buffer,
+            src_image,
+            request: 0,
+        }
+    }
+}
+
+#[derive(Clone, Default, Resource, Deref)]
+struct ImageCopiers(Vec<ImageCopier>);
+
+fn image_copy_extract(mut commands: Commands, image_copiers: Extract<Query<&ImageCopier>>) {
+    commands.insert_resource(ImageCopiers(image_copiers.iter().cloned().collect()));
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash, RenderLabel)]
+struct ImageCopy;
+
+#[derive(Default)]
+struct ImageCopyDriver;
+
+impl render_graph::Node for ImageCopyDriver {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let image_copiers = world.resource::<ImageCopiers>();
+        let gpu_images = world.resource::<RenderAssets<GpuImage>>();
+
+        for image_copier in image_copiers.iter() {
+            let Some(src_image) = gpu_images.get(&image_copier.src_image) else {
+                continue;
+            };
+
+            let mut encoder = render_context
+                .render_device()
+                .create_command_encoder(&CommandEncoderDescriptor::default());
+
+            // Rows copied into a buffer must be aligned, so the buffer may be wider than the image
+            let padded_bytes_per_row = RenderDevice::align_copy_bytes_per_row(
+                src_image.size.x as usize * src_image.texture_format.pixel_size(),
+            );
+
+            encoder.copy_texture_to_buffer(
+                src_image.texture.as_image_copy(),
+                ImageCopyBuffer {
+                    buffer: &image_copier.buffer,
+                    layout: ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(padded_bytes_per_row as u32),
+                        rows_per_image: None,
+                    },
+                },
+                Extent3d {
+                    width: src_image.size.x,
+                    height: src_image.size.y,
+                    depth_or_array_layers: 1,
+                },
+            );
+
+            world
+                .resource::<RenderQueue>()
+                .submit(std::iter::once(encoder.finish()));
+        }
+
+        Ok(())
+    }
+}
+
+fn receive_image_from_buffer(
+    image_copiers: Res<ImageCopiers>,
+    gpu_images: Res<RenderAssets<GpuImage>>,
+    pipeline_cache: Res<PipelineCache>,
+    render_device: Res<RenderDevice>,
+    sender: Res<RenderWorldSender>,
+) {
+    // Frames rendered while pipelines are still compiling may be missing the fullscreen quad
+    if pipeline_cache.waiting_pipelines().next().is_some() {
+        return;
+    }
+
+    for image_copier in image_copiers.iter() {
+        if gpu_images.get(&image_copier.src_image).is_none() {
+            continue;
+        }
+
+        let buffer_slice = image_copier.buffer.slice(..);
+
+        let (s, r) = crossbeam_channel::bounded(1);
+        buffer_slice.map_async(MapMode::Read, move |result| {
+            let _ = s.send(result);
+        });
+
+        // Block until the GPU has finished the copy and the buffer is mapped
+        render_device.poll(Maintain::wait()).panic_on_timeout();
+
+        if let Ok(Ok(())) = r.recv() {
+            // This can fail on exit, when the main world has already dropped the receiver
+            let _ = sender.send(CapturedImage {
+                request: image_copier.request,
+                data: buffer_slice.get_mapped_range().to_vec(),
+            });
+
+            image_copier.buffer.unmap();
+        }
+    }
+}
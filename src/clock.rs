@@ -0,0 +1,143 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::{
+    bevy_egui::EguiContexts, prelude::ReflectInspectorOptions, quick::ResourceInspectorPlugin,
+    InspectorOptions,
+};
+
+pub const CLOCK_DEFAULT_TIMESTEP: f32 = 1. / 120.;
+pub const CLOCK_MIN_TIMESTEP: f32 = 1. / 1000.;
+pub const CLOCK_MAX_TIME_SCALE: f32 = 8.;
+// Slowing down more than this would look like pausing
+pub const CLOCK_MIN_TIME_SCALE: f32 = 1. / 64.;
+
+pub const PAUSE_KEY: KeyCode = KeyCode::KeyP;
+pub const STEP_FORWARD_KEY: KeyCode = KeyCode::Period;
+pub const STEP_BACKWARD_KEY: KeyCode = KeyCode::Comma;
+pub const SPEED_UP_KEY: KeyCode = KeyCode::BracketRight;
+pub const SLOW_DOWN_KEY: KeyCode = KeyCode::BracketLeft;
+
+pub struct ShaderClockPlugin;
+
+impl Plugin for ShaderClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ShaderClock::default())
+            .register_type::<ShaderClock>()
+            .add_plugins(ResourceInspectorPlugin::<ShaderClock>::default())
+            .add_systems(
+                Update,
+                (control_clock_using_keyboard, advance_clock)
+                    .chain()
+                    .in_set(ShaderClockSet),
+            );
+    }
+}
+
+/// Systems which advance the `ShaderClock`, anything reading the clock should run after this
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ShaderClockSet;
+
+/// The time used by the shader, which only advances in fixed steps so animation is independent of frame rate
+#[derive(Debug, Clone, Resource, Reflect, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
+pub struct ShaderClock {
+    pub paused: bool,
+    // Negative scales play the animation backwards
+    #[inspector(min = -CLOCK_MAX_TIME_SCALE, max = CLOCK_MAX_TIME_SCALE)]
+    pub time_scale: f32,
+    #[inspector(min = CLOCK_MIN_TIMESTEP)]
+    pub fixed_timestep: f32,
+    // Drag to scrub through the timeline
+    #[inspector(min = 0., speed = 0.01)]
+    pub elapsed: f32,
+    #[reflect(ignore)]
+    accumulator: f32,
+    #[reflect(ignore)]
+    pending_steps: i32,
+    // The elapsed time after the last tick, if it is different at the next one it has been scrubbed
+    #[reflect(ignore)]
+    ticked_elapsed: f32,
+}
+
+impl Default for ShaderClock {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            time_scale: 1.,
+            fixed_timestep: CLOCK_DEFAULT_TIMESTEP,
+            elapsed: 0.,
+            accumulator: 0.,
+            pending_steps: 0,
+            ticked_elapsed: 0.,
+        }
+    }
+}
+
+impl ShaderClock {
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    // Queue single fixed steps, used to move frame by frame while paused
+    pub fn step(&mut self, steps: i32) {
+        self.pending_steps += steps;
+    }
+
+    // Jump to a point on the timeline, dropping any partial step
+    pub fn scrub_to(&mut self, elapsed: f32) {
+        self.elapsed = elapsed.max(0.);
+        self.accumulator = 0.;
+    }
+
+    // Advance by a real time delta, in whole fixed steps
+    pub fn tick(&mut self, delta_seconds: f32) {
+        if self.elapsed != self.ticked_elapsed {
+            self.scrub_to(self.elapsed);
+        }
+
+        let timestep = self.fixed_timestep.max(CLOCK_MIN_TIMESTEP);
+
+        if !self.paused {
+            self.accumulator += delta_seconds * self.time_scale;
+        }
+
+        // Truncating keeps the remainder's sign, so rewinding accumulates in the same way as playing
+        let steps = (self.accumulator / timestep).trunc() as i32;
+        self.accumulator -= steps as f32 * timestep;
+
+        let steps = steps + std::mem::take(&mut self.pending_steps);
+        self.elapsed = (self.elapsed + steps as f32 * timestep).max(0.);
+        self.ticked_elapsed = self.elapsed;
+    }
+}
+
+fn control_clock_using_keyboard(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut egui_contexts: EguiContexts,
+    mut clock: ResMut<ShaderClock>,
+) {
+    if egui_contexts.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+
+    for key in keys.get_just_pressed() {
+        match *key {
+            PAUSE_KEY => clock.toggle_pause(),
+            STEP_FORWARD_KEY => clock.step(1),
+            STEP_BACKWARD_KEY => clock.step(-1),
+            SPEED_UP_KEY => {
+                clock.time_scale =
+                    (clock.time_scale * 2.).clamp(-CLOCK_MAX_TIME_SCALE, CLOCK_MAX_TIME_SCALE)
+            }
+            // A stopped clock stays stopped, rather than taking the sign of zero and playing forwards
+            SLOW_DOWN_KEY if clock.time_scale != 0. => {
+                let time_scale = clock.time_scale / 2.;
+                clock.time_scale = time_scale.signum() * time_scale.abs().max(CLOCK_MIN_TIME_SCALE)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn advance_clock(time: Res<Time>, mut clock: ResMut<ShaderClock>) {
+    clock.tick(time.delta_seconds());
+}
@@ -0,0 +1,148 @@
+use bevy::{prelude::*, render::render_resource::ShaderType};
+use bevy_inspector_egui::{
+    bevy_egui::EguiContexts, prelude::ReflectInspectorOptions, InspectorOptions,
+};
+
+use crate::shader_material::ShaderMatInspector;
+
+// Pressing a number key shows the debug view with that number, 0 goes back to the shaded scene
+pub const DEBUG_VIEW_KEYS: [KeyCode; 8] = [
+    KeyCode::Digit0,
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+];
+
+pub struct DebugViewPlugin;
+
+impl Plugin for DebugViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, switch_debug_view_using_keyboard);
+    }
+}
+
+// What the shader shows instead of the shaded scene, to see what the ray march is doing
+#[derive(Debug, Copy, Clone, Default, Reflect, PartialEq, Eq)]
+pub enum DebugView {
+    #[default]
+    None,
+    // How many steps each ray took, as a heatmap
+    Steps,
+    Normals,
+    Depth,
+    // A different colour for each shape, from its ID
+    ShapeId,
+    // How close rays came to a shape
+    MinDist,
+    // Whether each point is in shadow
+    Shadow,
+    // The distance field on a horizontal plane
+    DistanceSlice,
+}
+
+#[derive(Debug, Copy, Clone, ShaderType)]
+pub struct ShaderDebug {
+    pub view: u32,
+    pub slice_height: f32,
+    pub depth_range: f32,
+}
+
+#[derive(Debug, Copy, Clone, Reflect, InspectorOptions)]
+#[reflect(InspectorOptions)]
+pub struct ShaderDebugInspector {
+    pub view: DebugView,
+    // The height of the plane the distance slice shows
+    pub slice_height: f32,
+    // How far away shapes are black in the depth view
+    #[inspector(min = 0.1)]
+    pub depth_range: f32,
+}
+
+impl Default for ShaderDebug {
+    fn default() -> Self {
+        Self {
+            view: DebugView::None.into(),
+            slice_height: 0.,
+            depth_range: 50.,
+        }
+    }
+}
+
+impl Default for ShaderDebugInspector {
+    fn default() -> Self {
+        ShaderDebug::default().into()
+    }
+}
+
+impl From<DebugView> for u32 {
+    fn from(view: DebugView) -> Self {
+        match view {
+            DebugView::None => 0,
+            DebugView::Steps => 1,
+            DebugView::Normals => 2,
+            DebugView::Depth => 3,
+            DebugView::ShapeId => 4,
+            DebugView::MinDist => 5,
+            DebugView::Shadow => 6,
+            DebugView::DistanceSlice => 7,
+        }
+    }
+}
+
+impl From<u32> for DebugView {
+    fn from(view: u32) -> Self {
+        match view {
+            1 => Self::Steps,
+            2 => Self::Normals,
+            3 => Self::Depth,
+            4 => Self::ShapeId,
+            5 => Self::MinDist,
+            6 => Self::Shadow,
+            7 => Self::DistanceSlice,
+            _ => Self::None,
+        }
+    }
+}
+
+impl From<ShaderDebugInspector> for ShaderDebug {
+    fn from(inspector: ShaderDebugInspector) -> Self {
+        Self {
+            view: inspector.view.into(),
+            slice_height: inspector.slice_height,
+            depth_range: inspector.depth_range,
+        }
+    }
+}
+
+impl From<ShaderDebug> for ShaderDebugInspector {
+    fn from(debug: ShaderDebug) -> Self {
+        Self {
+            view: debug.view.into(),
+            slice_height: debug.slice_height,
+            depth_range: debug.depth_range,
+        }
+    }
+}
+
+// Goes through the inspector, so it stays in sync with the shader. Digits typed into the inspector are left alone
+fn switch_debug_view_using_keyboard(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut egui_contexts: EguiContexts,
+    mut inspector_mat: ResMut<ShaderMatInspector>,
+) {
+    if egui_contexts.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+
+    let pressed = DEBUG_VIEW_KEYS
+        .iter()
+        .position(|key| keys.just_pressed(*key));
+
+    if let Some(index) = pressed {
+        inspector_mat.debug.view = DebugView::from(index as u32);
+    }
+}
@@ -0,0 +1,231 @@
+use bevy::{prelude::*, render::render_resource::ShaderType};
+use bevy_inspector_egui::{prelude::ReflectInspectorOptions, InspectorOptions};
+
+use crate::shader_material::vec_to_sized_array;
+
+// How many volumes fog.wgsl has room for
+pub const MAX_VOLUMES: usize = 4;
+
+#[derive(Debug, Copy, Clone, Default, Reflect, PartialEq, Eq)]
+pub enum VolumeShape {
+    #[default]
+    Sphere,
+    Box,
+}
+
+// A region of cloud or smoke, lit by the light and the sky as it is marched through
+#[derive(Debug, Copy, Clone, ShaderType)]
+pub struct ShaderVolume {
+    pub shape: u32,
+    pub pos: Vec3,
+    pub size: Vec3,
+    pub density: f32,
+    pub colour: Vec3,
+    pub noise_frequency: f32,
+    pub edge_softness: f32,
+    pub anisotropy: f32,
+    pub drift: Vec3,
+}
+
+#[derive(Debug, Copy, Clone, Reflect, InspectorOptions)]
+#[reflect(InspectorOptions)]
+pub struct ShaderVolumeInspector {
+    pub shape: VolumeShape,
+    pub pos: Vec3,
+    // The radius in x for spheres, or the half extents of boxes
+    pub size: Vec3,
+    #[inspector(min = 0.)]
+    pub density: f32,
+    // How much light is scattered rather than absorbed
+    pub colour: Vec3,
+    // Zero gives an even density, otherwise noise breaks the volume up into clouds
+    #[inspector(min = 0.)]
+    pub noise_frequency: f32,
+    // How far inside the edge the density takes to reach its full value
+    #[inspector(min = 0.)]
+    pub edge_softness: f32,
+    // Positive values scatter light forwards, giving a bright rim when looking towards the light
+    #[inspector(min = -0.95, max = 0.95)]
+    pub anisotropy: f32,
+    // How fast the noise moves through the volume
+    pub drift: Vec3,
+}
+
+// Distance and height fog applied to the shapes, and the volumes marched on top of everything
+#[derive(Debug, Copy, Clone, ShaderType)]
+pub struct ShaderFog {
+    pub density: f32,
+    pub height_density: f32,
+    pub height: f32,
+    pub height_falloff: f32,
+    pub colour: Vec3,
+    pub match_sky: u32,
+    pub sun_scattering: f32,
+    pub volume_steps: u32,
+    pub volumes_len: u32,
+    pub volumes: [ShaderVolume; MAX_VOLUMES],
+}
+
+#[derive(Debug, Clone, Reflect, InspectorOptions)]
+#[reflect(InspectorOptions)]
+pub struct ShaderFogInspector {
+    // Exponential fog, that thickens evenly with distance
+    #[inspector(min = 0.)]
+    pub density: f32,
+    // Height fog, which is thickest below the height and thins out above it at the falloff rate
+    #[inspector(min = 0.)]
+    pub height_density: f32,
+    pub height: f32,
+    #[inspector(min = 0.)]
+    pub height_falloff: f32,
+    pub colour: Vec3,
+    // Use the sky's horizon colour instead of the colour, so distant shapes fade into the sky
+    pub match_sky: bool,
+    // How much the fog glows around the sun
+    #[inspector(min = 0.)]
+    pub sun_scattering: f32,
+    // Samples taken through the volumes along each ray
+    #[inspector(min = 1, max = 256)]
+    pub volume_steps: u32,
+    // Only the first MAX_VOLUMES are used
+    pub volumes: Vec<ShaderVolumeInspector>,
+}
+
+impl Default for ShaderVolume {
+    fn default() -> Self {
+        Self {
+            shape: VolumeShape::default().into(),
+            pos: Vec3::ZERO,
+            size: Vec3::splat(2.),
+            density: 1.,
+            colour: Vec3::ONE,
+            noise_frequency: 0.5,
+            edge_softness: 0.5,
+            anisotropy: 0.3,
+            drift: Vec3::new(0.2, 0., 0.),
+        }
+    }
+}
+
+impl Default for ShaderVolumeInspector {
+    fn default() -> Self {
+        ShaderVolume::default().into()
+    }
+}
+
+impl Default for ShaderFog {
+    fn default() -> Self {
+        Self {
+            density: 0.01,
+            height_density: 0.05,
+            height: -3.,
+            height_falloff: 0.5,
+            colour: Vec3::new(0.5, 0.6, 0.7),
+            match_sky: true.into(),
+            sun_scattering: 0.5,
+            volume_steps: 32,
+            volumes_len: 0,
+            volumes: [ShaderVolume::default(); MAX_VOLUMES],
+        }
+    }
+}
+
+impl Default for ShaderFogInspector {
+    fn default() -> Self {
+        ShaderFog::default().into()
+    }
+}
+
+impl From<VolumeShape> for u32 {
+    fn from(shape: VolumeShape) -> Self {
+        match shape {
+            VolumeShape::Sphere => 0,
+            VolumeShape::Box => 1,
+        }
+    }
+}
+
+impl From<u32> for VolumeShape {
+    fn from(shape: u32) -> Self {
+        match shape {
+            1 => Self::Box,
+            _ => Self::Sphere,
+        }
+    }
+}
+
+impl From<ShaderVolumeInspector> for ShaderVolume {
+    fn from(inspector: ShaderVolumeInspector) -> Self {
+        Self {
+            shape: inspector.shape.into(),
+            pos: inspector.pos,
+            size: inspector.size,
+            density: inspector.density,
+            colour: inspector.colour,
+            noise_frequency: inspector.noise_frequency,
+            edge_softness: inspector.edge_softness,
+            anisotropy: inspector.anisotropy,
+            drift: inspector.drift,
+        }
+    }
+}
+
+impl From<ShaderVolume> for ShaderVolumeInspector {
+    fn from(volume: ShaderVolume) -> Self {
+        Self {
+            shape: volume.shape.into(),
+            pos: volume.pos,
+            size: volume.size,
+            density: volume.density,
+            colour: volume.colour,
+            noise_frequency: volume.noise_frequency,
+            edge_softness: volume.edge_softness,
+            anisotropy: volume.anisotropy,
+            drift: volume.drift,
+        }
+    }
+}
+
+impl From<ShaderFogInspector> for ShaderFog {
+    fn from(inspector: ShaderFogInspector) -> Self {
+        let volumes_len = inspector.volumes.len().min(MAX_VOLUMES) as u32;
+
+        Self {
+            density: inspector.density,
+            height_density: inspector.height_density,
+            height: inspector.height,
+            height_falloff: inspector.height_falloff,
+            colour: inspector.colour,
+            match_sky: inspector.match_sky.into(),
+            sun_scattering: inspector.sun_scattering,
+            volume_steps: inspector.volume_steps,
+            volumes_len,
+            volumes: vec_to_sized_array(
+                inspector
+                    .volumes
+                    .into_iter()
+                    .map(|volume| volume.into())
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<ShaderFog> for ShaderFogInspector {
+    fn from(fog: ShaderFog) -> Self {
+        Self {
+            density: fog.density,
+            height_density: fog.height_density,
+            height: fog.height,
+            height_falloff: fog.height_falloff,
+            colour: fog.colour,
+            match_sky: fog.match_sky != 0,
+            sun_scattering: fog.sun_scattering,
+            volume_steps: fog.volume_steps,
+            volumes: fog.volumes[..(fog.volumes_len as usize).min(MAX_VOLUMES)]
+                .iter()
+                .map(|&volume| volume.into())
+                .collect(),
+        }
+    }
+}
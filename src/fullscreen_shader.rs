@@ -49,7 +49,7 @@ where
 pub struct FullscreenShader<S: Material2d>(S);
 
 #[derive(Component)]
-struct FullscreenCover;
+pub struct FullscreenCover;
 
 impl<S: Material2d> FullscreenShader<S> {
     fn setup(
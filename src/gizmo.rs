@@ -0,0 +1,639 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_inspector_egui::{
+    bevy_egui::EguiContexts, prelude::ReflectInspectorOptions, quick::ResourceInspectorPlugin,
+    InspectorOptions,
+};
+
+use crate::{
+    camera_controller::{CameraProjection, ShaderCamera},
+    fullscreen_shader::FullscreenCover,
+    light::LightType,
+    picking::{ShapePickingSet, ShapeSelection, PICK_BUTTON},
+    sdf::animate_shape,
+    shader_material::{ShaderMat, ShaderMatInspector},
+    shape::Shape,
+};
+
+pub const TRANSLATE_KEY: KeyCode = KeyCode::KeyT;
+pub const ROTATE_KEY: KeyCode = KeyCode::KeyR;
+pub const SCALE_KEY: KeyCode = KeyCode::KeyY;
+// Hold to do the opposite of GizmoSettings::snapping
+pub const SNAP_KEY: KeyCode = KeyCode::AltLeft;
+
+// Sizes on screen, in pixels
+pub const GIZMO_HANDLE_LENGTH: f32 = 80.;
+pub const GIZMO_PICK_DISTANCE: f32 = 8.;
+pub const GIZMO_CENTRE_SIZE: f32 = 10.;
+pub const LIGHT_MARKER_RADIUS: f32 = 10.;
+const RING_SEGMENTS: usize = 48;
+
+const AXIS_COLOURS: [Color; 3] = [
+    Color::srgb(0.9, 0.2, 0.2),
+    Color::srgb(0.2, 0.9, 0.2),
+    Color::srgb(0.2, 0.4, 0.9),
+];
+const CENTRE_COLOUR: Color = Color::srgb(0.9, 0.9, 0.9);
+const HIGHLIGHT_COLOUR: Color = Color::srgb(1., 0.85, 0.1);
+
+pub struct TransformGizmoPlugin;
+
+impl Plugin for TransformGizmoPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GizmoSettings::default())
+            .insert_resource(GizmoState::default())
+            .register_type::<GizmoSettings>()
+            .add_plugins(ResourceInspectorPlugin::<GizmoSettings>::default())
+            .add_systems(
+                Update,
+                (
+                    (switch_gizmo_mode_using_keyboard, drag_gizmo)
+                        .chain()
+                        .before(ShapePickingSet),
+                    draw_gizmo.after(ShapePickingSet),
+                ),
+            );
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, Reflect, PartialEq, Eq)]
+pub enum GizmoMode {
+    #[default]
+    Translate,
+    Rotate,
+    Scale,
+}
+
+/// How the selected shape or light is moved with the mouse, dragging an axis handle constrains the change to that axis
+#[derive(Debug, Clone, Resource, Reflect, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
+pub struct GizmoSettings {
+    pub mode: GizmoMode,
+    pub snapping: bool,
+    #[inspector(min = 0.01)]
+    pub translate_snap: f32,
+    // In degrees
+    #[inspector(min = 1., max = 90.)]
+    pub rotate_snap: f32,
+    #[inspector(min = 0.01)]
+    pub scale_snap: f32,
+}
+
+impl Default for GizmoSettings {
+    fn default() -> Self {
+        Self {
+            mode: GizmoMode::Translate,
+            snapping: false,
+            translate_snap: 0.25,
+            rotate_snap: 15.,
+            scale_snap: 0.1,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GizmoHandle {
+    Axis(usize),
+    // Moves in the plane of the screen, or scales evenly
+    Centre,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct GizmoDrag {
+    handle: GizmoHandle,
+    start_cursor: Vec2,
+    start: GizmoTransform,
+}
+
+/// The handle under the cursor and the drag in progress, picking ignores clicks while either is set
+#[derive(Debug, Default, Resource)]
+pub struct GizmoState {
+    pub hovered: Option<GizmoHandle>,
+    drag: Option<GizmoDrag>,
+}
+
+impl GizmoState {
+    pub fn is_active(&self) -> bool {
+        self.hovered.is_some() || self.drag.is_some()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum GizmoTarget {
+    Shape(usize),
+    Light,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct GizmoTransform {
+    pos: Vec3,
+    rotation: Quat,
+    size: Vec3,
+}
+
+fn gizmo_target(
+    selection: &ShapeSelection,
+    inspector_mat: &ShaderMatInspector,
+) -> Option<GizmoTarget> {
+    if selection.light_selected {
+        // Directional lights come from the sky, so have no position to move
+        return (inspector_mat.light.light_type == LightType::Point).then_some(GizmoTarget::Light);
+    }
+
+    selection
+        .selected
+        .filter(|&index| {
+            index < inspector_mat.shapes.len() && !inspector_mat.is_shape_locked(index)
+        })
+        .map(GizmoTarget::Shape)
+}
+
+// How far the shape's animation has moved it from where the inspector puts it, so the gizmo stays on the shape as drawn
+fn animation_offset(target: GizmoTarget, inspector_mat: &ShaderMatInspector, time: f32) -> Vec3 {
+    match target {
+        GizmoTarget::Shape(index) => {
+            let shape = Shape::from(inspector_mat.shapes[index].clone());
+
+            animate_shape(shape, time).pos - shape.pos
+        }
+        GizmoTarget::Light => Vec3::ZERO,
+    }
+}
+
+fn get_transform(
+    target: GizmoTarget,
+    inspector_mat: &ShaderMatInspector,
+    time: f32,
+) -> GizmoTransform {
+    match target {
+        GizmoTarget::Shape(index) => {
+            let shape = &inspector_mat.shapes[index];
+
+            GizmoTransform {
+                pos: shape.pos + animation_offset(target, inspector_mat, time),
+                rotation: shape.rotation,
+                size: shape.size,
+            }
+        }
+        GizmoTarget::Light => GizmoTransform {
+            pos: inspector_mat.light.pos,
+            rotation: Quat::IDENTITY,
+            size: Vec3::ONE,
+        },
+    }
+}
+
+// The animation is taken off again, so a dragged shape stays under the cursor while it would otherwise be moving
+fn set_transform(
+    target: GizmoTarget,
+    transform: GizmoTransform,
+    inspector_mat: &mut ShaderMatInspector,
+    time: f32,
+) {
+    match target {
+        GizmoTarget::Shape(index) => {
+            let offset = animation_offset(target, inspector_mat, time);
+            let shape = &mut inspector_mat.shapes[index];
+            shape.pos = transform.pos - offset;
+            shape.rotation = transform.rotation;
+            shape.size = transform.size;
+        }
+        GizmoTarget::Light => inspector_mat.light.pos = transform.pos,
+    }
+}
+
+// Lights can only be moved
+fn target_mode(target: GizmoTarget, mode: GizmoMode) -> GizmoMode {
+    match target {
+        GizmoTarget::Light => GizmoMode::Translate,
+        GizmoTarget::Shape(_) => mode,
+    }
+}
+
+// Where a point appears on the screen, in pixels from the top left. The inverse of get_ray_dir and
+// centre_and_scale_uv_positions, returning None for points behind the camera or closer than its near distance
+pub fn world_to_screen(camera: &ShaderCamera, p: Vec3, screen_dim: Vec2) -> Option<Vec2> {
+    let offset = p - camera.pos;
+    let depth = offset.dot(camera.forward);
+    let across = Vec2::new(offset.dot(camera.right), offset.dot(camera.up));
+
+    let mut coord = match CameraProjection::from(camera.projection) {
+        CameraProjection::Perspective => {
+            if depth <= camera.near.max(1e-3) {
+                return None;
+            }
+            across * camera.focal_length() / depth
+        }
+        CameraProjection::Orthographic => {
+            if depth <= camera.near {
+                return None;
+            }
+            across / camera.ortho_size
+        }
+        CameraProjection::Fisheye => {
+            if offset.length() <= camera.near.max(1e-3) {
+                return None;
+            }
+            let angle = offset.angle_between(camera.forward);
+            across.normalize_or_zero() * angle / (camera.fov * 0.5)
+        }
+        CameraProjection::Equirectangular => {
+            if offset.length() <= camera.near.max(1e-3) {
+                return None;
+            }
+            let longitude = across.x.atan2(depth);
+            let latitude = (across.y / offset.length()).asin();
+            Vec2::new(longitude, latitude) / FRAC_PI_2
+        }
+    };
+
+    let min_screen_size = screen_dim.min_element();
+    let centre_push = (screen_dim.max_element() - min_screen_size) / min_screen_size;
+    if screen_dim.x > screen_dim.y {
+        coord.x += centre_push;
+    } else if screen_dim.x < screen_dim.y {
+        coord.y -= centre_push;
+    }
+
+    let uv = (coord + 1.) * min_screen_size / (2. * screen_dim);
+
+    Some(Vec2::new(uv.x, 1. - uv.y) * screen_dim)
+}
+
+// The size of a pixel in world units, at the distance of a point
+fn world_per_pixel(camera: &ShaderCamera, p: Vec3, screen_dim: Vec2) -> f32 {
+    let offset = p - camera.pos;
+
+    let world_per_coord = match CameraProjection::from(camera.projection) {
+        CameraProjection::Perspective => {
+            offset.dot(camera.forward).max(1e-3) / camera.focal_length()
+        }
+        CameraProjection::Orthographic => camera.ortho_size,
+        CameraProjection::Fisheye => offset.length() * camera.fov * 0.5,
+        CameraProjection::Equirectangular => offset.length() * FRAC_PI_2,
+    };
+
+    world_per_coord * 2. / screen_dim.min_element()
+}
+
+// The directions of the handles, scaling is along the shape's own axes
+fn handle_axes(mode: GizmoMode, rotation: Quat) -> [Vec3; 3] {
+    match mode {
+        GizmoMode::Scale => [rotation * Vec3::X, rotation * Vec3::Y, rotation * Vec3::Z],
+        _ => [Vec3::X, Vec3::Y, Vec3::Z],
+    }
+}
+
+// The outline of a handle on screen, a line for moving and scaling and a ring for rotating
+fn handle_points(
+    camera: &ShaderCamera,
+    screen_dim: Vec2,
+    mode: GizmoMode,
+    transform: &GizmoTransform,
+    axis: Vec3,
+) -> Vec<Vec2> {
+    let length = GIZMO_HANDLE_LENGTH * world_per_pixel(camera, transform.pos, screen_dim);
+
+    let points = match mode {
+        GizmoMode::Rotate => {
+            let (u, v) = axis.any_orthonormal_pair();
+
+            (0..=RING_SEGMENTS)
+                .map(|i| {
+                    let (sin, cos) = (i as f32 / RING_SEGMENTS as f32 * TAU).sin_cos();
+                    transform.pos + (u * cos + v * sin) * length
+                })
+                .collect::<Vec<_>>()
+        }
+        _ => vec![transform.pos, transform.pos + axis * length],
+    };
+
+    points
+        .into_iter()
+        .filter_map(|p| world_to_screen(camera, p, screen_dim))
+        .collect()
+}
+
+fn distance_to_segment(p: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let ab = b - a;
+    let t = ((p - a).dot(ab) / ab.length_squared().max(1e-6)).clamp(0., 1.);
+
+    p.distance(a + ab * t)
+}
+
+fn handle_under_cursor(
+    camera: &ShaderCamera,
+    screen_dim: Vec2,
+    mode: GizmoMode,
+    transform: &GizmoTransform,
+    cursor: Vec2,
+) -> Option<GizmoHandle> {
+    let centre = world_to_screen(camera, transform.pos, screen_dim)?;
+
+    if mode != GizmoMode::Rotate && (cursor - centre).abs().max_element() <= GIZMO_CENTRE_SIZE {
+        return Some(GizmoHandle::Centre);
+    }
+
+    handle_axes(mode, transform.rotation)
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, axis)| {
+            let points = handle_points(camera, screen_dim, mode, transform, axis);
+            let distance = points
+                .windows(2)
+                .map(|segment| distance_to_segment(cursor, segment[0], segment[1]))
+                .fold(f32::INFINITY, f32::min);
+
+            (distance <= GIZMO_PICK_DISTANCE).then_some((i, distance))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(i, _)| GizmoHandle::Axis(i))
+}
+
+fn snap(value: f32, step: f32) -> f32 {
+    (value / step).round() * step
+}
+
+// The transform after dragging a handle from the start of the drag to the cursor
+fn dragged_transform(
+    camera: &ShaderCamera,
+    screen_dim: Vec2,
+    mode: GizmoMode,
+    drag: &GizmoDrag,
+    cursor: Vec2,
+    settings: &GizmoSettings,
+    snapping: bool,
+) -> GizmoTransform {
+    let start = drag.start;
+    let mut transform = start;
+    let delta = cursor - drag.start_cursor;
+    let pixel = world_per_pixel(camera, start.pos, screen_dim);
+    let length = GIZMO_HANDLE_LENGTH * pixel;
+
+    // How far along an axis the cursor has moved, in handle lengths
+    let along_axis = |axis: Vec3| {
+        let screen_start = world_to_screen(camera, start.pos, screen_dim)?;
+        let screen_axis =
+            world_to_screen(camera, start.pos + axis * length, screen_dim)? - screen_start;
+
+        (screen_axis.length_squared() > 1.)
+            .then(|| delta.dot(screen_axis) / screen_axis.length_squared())
+    };
+
+    match (mode, drag.handle) {
+        (GizmoMode::Translate, GizmoHandle::Axis(i)) => {
+            if let Some(amount) = along_axis(Vec3::AXES[i]) {
+                transform.pos[i] = start.pos[i] + amount * length;
+                if snapping {
+                    transform.pos[i] = snap(transform.pos[i], settings.translate_snap);
+                }
+            }
+        }
+        (GizmoMode::Translate, GizmoHandle::Centre) => {
+            // Move in the plane of the screen, screen y is downwards
+            transform.pos = start.pos + (camera.right * delta.x - camera.up * delta.y) * pixel;
+            if snapping {
+                transform.pos =
+                    (transform.pos / settings.translate_snap).round() * settings.translate_snap;
+            }
+        }
+        (GizmoMode::Rotate, GizmoHandle::Axis(i)) => {
+            let Some(centre) = world_to_screen(camera, start.pos, screen_dim) else {
+                return transform;
+            };
+
+            // The angle swept around the centre, anticlockwise on screen
+            let from = (drag.start_cursor - centre) * Vec2::new(1., -1.);
+            let to = (cursor - centre) * Vec2::new(1., -1.);
+            let mut angle = from.perp_dot(to).atan2(from.dot(to));
+
+            // Anticlockwise on screen is a positive rotation about axes pointing away from the camera
+            let axis = Vec3::AXES[i];
+            if axis.dot(start.pos - camera.pos) < 0. {
+                angle = -angle;
+            }
+            if snapping {
+                angle = snap(angle.to_degrees(), settings.rotate_snap).to_radians();
+            }
+
+            transform.rotation = (Quat::from_axis_angle(axis, angle) * start.rotation).normalize();
+        }
+        (GizmoMode::Scale, GizmoHandle::Axis(i)) => {
+            let axis = handle_axes(mode, start.rotation)[i];
+            if let Some(amount) = along_axis(axis) {
+                transform.size[i] = start.size[i] * (1. + amount);
+                if snapping {
+                    transform.size[i] = snap(transform.size[i], settings.scale_snap);
+                }
+                transform.size[i] = transform.size[i].max(0.01);
+            }
+        }
+        (GizmoMode::Scale, GizmoHandle::Centre) => {
+            let factor = 1. + (delta.x - delta.y) / GIZMO_HANDLE_LENGTH;
+            transform.size = start.size * factor;
+            if snapping {
+                transform.size =
+                    (transform.size / settings.scale_snap).round() * settings.scale_snap;
+            }
+            transform.size = transform.size.max(Vec3::splat(0.01));
+        }
+        (GizmoMode::Rotate, GizmoHandle::Centre) => {}
+    }
+
+    transform
+}
+
+fn switch_gizmo_mode_using_keyboard(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut egui_contexts: EguiContexts,
+    mut settings: ResMut<GizmoSettings>,
+) {
+    // Letters typed into the inspector are left alone
+    if egui_contexts.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+
+    for key in keys.get_just_pressed() {
+        match *key {
+            TRANSLATE_KEY => settings.mode = GizmoMode::Translate,
+            ROTATE_KEY => settings.mode = GizmoMode::Rotate,
+            SCALE_KEY => settings.mode = GizmoMode::Scale,
+            _ => {}
+        }
+    }
+}
+
+// Find the handle under the cursor, start dragging it when clicked and move the target while it is held
+#[allow(clippy::too_many_arguments)]
+fn drag_gizmo(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut egui_contexts: EguiContexts,
+    cover: Query<&Handle<ShaderMat>, With<FullscreenCover>>,
+    shader_mats: Res<Assets<ShaderMat>>,
+    selection: Res<ShapeSelection>,
+    settings: Res<GizmoSettings>,
+    mut state: ResMut<GizmoState>,
+    mut inspector_mat: ResMut<ShaderMatInspector>,
+) {
+    let (Ok(window), Some(mat)) = (
+        windows.get_single(),
+        cover
+            .get_single()
+            .ok()
+            .and_then(|handle| shader_mats.get(handle)),
+    ) else {
+        return;
+    };
+
+    let Some(target) = gizmo_target(&selection, &inspector_mat) else {
+        *state = GizmoState::default();
+        return;
+    };
+
+    let screen_dim = window.size();
+    let mode = target_mode(target, settings.mode);
+    let cursor = window.cursor_position().filter(|_| window.cursor.visible);
+
+    if let Some(drag) = state.drag {
+        match cursor.filter(|_| mouse_buttons.pressed(PICK_BUTTON)) {
+            Some(cursor) => {
+                let snapping = settings.snapping != keys.pressed(SNAP_KEY);
+                let transform = dragged_transform(
+                    &mat.camera,
+                    screen_dim,
+                    mode,
+                    &drag,
+                    cursor,
+                    &settings,
+                    snapping,
+                );
+
+                // Only touch the inspector when something moved, as that updates the shader
+                if transform != get_transform(target, &inspector_mat, mat.time) {
+                    set_transform(target, transform, &mut inspector_mat, mat.time);
+                }
+            }
+            None => state.drag = None,
+        }
+
+        return;
+    }
+
+    let hovered = cursor
+        .filter(|_| !egui_contexts.ctx_mut().is_pointer_over_area())
+        .and_then(|cursor| {
+            let transform = get_transform(target, &inspector_mat, mat.time);
+            handle_under_cursor(&mat.camera, screen_dim, mode, &transform, cursor)
+        });
+    state.hovered = hovered;
+
+    if let (Some(handle), Some(cursor)) = (hovered, cursor) {
+        if mouse_buttons.just_pressed(PICK_BUTTON) {
+            state.drag = Some(GizmoDrag {
+                handle,
+                start_cursor: cursor,
+                start: get_transform(target, &inspector_mat, mat.time),
+            });
+        }
+    }
+}
+
+// Gizmos are drawn by the 2D camera, whose origin is the centre of the screen with y upwards
+fn screen_to_gizmo(p: Vec2, screen_dim: Vec2) -> Vec2 {
+    Vec2::new(p.x - screen_dim.x / 2., screen_dim.y / 2. - p.y)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_gizmo(
+    mut gizmos: Gizmos,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cover: Query<&Handle<ShaderMat>, With<FullscreenCover>>,
+    shader_mats: Res<Assets<ShaderMat>>,
+    selection: Res<ShapeSelection>,
+    settings: Res<GizmoSettings>,
+    state: Res<GizmoState>,
+    inspector_mat: Res<ShaderMatInspector>,
+) {
+    let (Ok(window), Some(mat)) = (
+        windows.get_single(),
+        cover
+            .get_single()
+            .ok()
+            .and_then(|handle| shader_mats.get(handle)),
+    ) else {
+        return;
+    };
+
+    let screen_dim = window.size();
+    let camera = &mat.camera;
+
+    // Mark where the point light is, so it can be clicked on
+    if inspector_mat.light.light_type == LightType::Point {
+        if let Some(light) = world_to_screen(camera, inspector_mat.light.pos, screen_dim) {
+            let colour = if selection.light_selected {
+                HIGHLIGHT_COLOUR
+            } else {
+                CENTRE_COLOUR
+            };
+            gizmos.circle_2d(
+                screen_to_gizmo(light, screen_dim),
+                LIGHT_MARKER_RADIUS,
+                colour,
+            );
+        }
+    }
+
+    let Some(target) = gizmo_target(&selection, &inspector_mat) else {
+        return;
+    };
+
+    let mode = target_mode(target, settings.mode);
+    let transform = get_transform(target, &inspector_mat, mat.time);
+    let active = state.drag.map(|drag| drag.handle).or(state.hovered);
+
+    for (i, axis) in handle_axes(mode, transform.rotation)
+        .into_iter()
+        .enumerate()
+    {
+        let colour = if active == Some(GizmoHandle::Axis(i)) {
+            HIGHLIGHT_COLOUR
+        } else {
+            AXIS_COLOURS[i]
+        };
+
+        let points = handle_points(camera, screen_dim, mode, &transform, axis);
+        gizmos.linestrip_2d(
+            points.iter().map(|&p| screen_to_gizmo(p, screen_dim)),
+            colour,
+        );
+
+        // Boxes on the end of scale handles, to tell them apart from moving
+        if let (GizmoMode::Scale, Some(&end)) = (mode, points.last()) {
+            gizmos.rect_2d(
+                screen_to_gizmo(end, screen_dim),
+                0.,
+                Vec2::splat(8.),
+                colour,
+            );
+        }
+    }
+
+    if mode != GizmoMode::Rotate {
+        if let Some(centre) = world_to_screen(camera, transform.pos, screen_dim) {
+            let colour = if active == Some(GizmoHandle::Centre) {
+                HIGHLIGHT_COLOUR
+            } else {
+                CENTRE_COLOUR
+            };
+            gizmos.rect_2d(
+                screen_to_gizmo(centre, screen_dim),
+                0.,
+                Vec2::splat(GIZMO_CENTRE_SIZE * 2.),
+                colour,
+            );
+        }
+    }
+}
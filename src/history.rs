@@ -0,0 +1,194 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::bevy_egui::EguiContexts;
+
+use crate::{
+    camera_controller::ShaderCameraInspector, light::ShaderLightInspector, outliner::ShapeGroup,
+    shader_material::ShaderMatInspector, shape::ShapeInspector, UnionType,
+};
+
+// Ctrl + Z undoes, Ctrl + Shift + Z redoes
+pub const UNDO_KEY: KeyCode = KeyCode::KeyZ;
+// Edits of the same kind closer together than this, or made while a mouse button is held, are merged into one step so
+// dragging a slider or gizmo is undone all at once
+pub const COALESCE_SECONDS: f32 = 0.5;
+pub const MAX_HISTORY_STEPS: usize = 100;
+
+pub struct SceneHistoryPlugin;
+
+impl Plugin for SceneHistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SceneHistory::default())
+            .add_systems(Update, update_scene_history);
+    }
+}
+
+/// The parts of the `ShaderMatInspector` which make up the scene, rather than how it is drawn
+#[derive(Debug, Clone, Reflect)]
+pub struct SceneSnapshot {
+    pub shapes: Vec<ShapeInspector>,
+    pub groups: Vec<ShapeGroup>,
+    pub union_type: UnionType,
+    pub smoothness_val: f32,
+    pub light: ShaderLightInspector,
+    pub camera: ShaderCameraInspector,
+}
+
+impl SceneSnapshot {
+    pub fn apply(&self, inspector_mat: &mut ShaderMatInspector) {
+        inspector_mat.shapes = self.shapes.clone();
+        inspector_mat.groups = self.groups.clone();
+        inspector_mat.union_type = self.union_type;
+        inspector_mat.smoothness_val = self.smoothness_val;
+        inspector_mat.light = self.light;
+        inspector_mat.camera = self.camera;
+    }
+
+    // Compared through reflection, as not every inspector type implements PartialEq
+    fn matches(&self, other: &Self) -> bool {
+        self.reflect_partial_eq(other).unwrap_or(false)
+    }
+}
+
+impl From<&ShaderMatInspector> for SceneSnapshot {
+    fn from(inspector_mat: &ShaderMatInspector) -> Self {
+        Self {
+            shapes: inspector_mat.shapes.clone(),
+            groups: inspector_mat.groups.clone(),
+            union_type: inspector_mat.union_type,
+            smoothness_val: inspector_mat.smoothness_val,
+            light: inspector_mat.light,
+            camera: inspector_mat.camera,
+        }
+    }
+}
+
+// What an edit changed, so flying the camera and editing the inspector straight after are undone separately
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum EditKind {
+    Camera,
+    Inspector,
+}
+
+/// Snapshots of the scene before and after the current one, which edits are pushed onto once they settle
+#[derive(Debug, Default, Resource)]
+pub struct SceneHistory {
+    undo: Vec<SceneSnapshot>,
+    redo: Vec<SceneSnapshot>,
+    // The scene as of the last step, None until the first update
+    current: Option<SceneSnapshot>,
+    // The kind of edit which hasn't been made into a step yet, and when the latest one was made
+    pending: Option<(EditKind, f32)>,
+    // The inspector camera last update, as the camera controller moves it without marking the inspector changed
+    last_camera: Option<ShaderCameraInspector>,
+}
+
+impl SceneHistory {
+    fn commit(&mut self, snapshot: SceneSnapshot) {
+        self.pending = None;
+
+        let Some(current) = self.current.as_mut() else {
+            self.current = Some(snapshot);
+            return;
+        };
+
+        // Changes to settings which aren't part of the scene don't make a step
+        if current.matches(&snapshot) {
+            return;
+        }
+
+        self.undo.push(std::mem::replace(current, snapshot));
+        self.redo.clear();
+
+        if self.undo.len() > MAX_HISTORY_STEPS {
+            self.undo.remove(0);
+        }
+    }
+
+    fn step(&mut self, undo: bool) -> Option<&SceneSnapshot> {
+        let (from, to) = if undo {
+            (&mut self.undo, &mut self.redo)
+        } else {
+            (&mut self.redo, &mut self.undo)
+        };
+
+        let snapshot = from.pop()?;
+        let current = self.current.as_mut()?;
+        to.push(std::mem::replace(current, snapshot));
+
+        self.current.as_ref()
+    }
+}
+
+// Turn settled edits into steps, and undo or redo them with the keyboard. Changes made here aren't seen by this
+// system's change detection, so restoring a snapshot doesn't make a new step
+fn update_scene_history(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    time: Res<Time<Real>>,
+    mut egui_contexts: EguiContexts,
+    mut history: ResMut<SceneHistory>,
+    mut inspector_mat: ResMut<ShaderMatInspector>,
+) {
+    let now = time.elapsed_seconds();
+
+    if history.current.is_none() {
+        history.commit(SceneSnapshot::from(&*inspector_mat));
+    }
+
+    // Flying around makes a step once the camera stops, like any other edit
+    let last_camera = history.last_camera;
+    let camera_moved = last_camera.is_some_and(|camera| camera != inspector_mat.camera);
+    history.last_camera = Some(inspector_mat.camera);
+
+    let kind = if inspector_mat.is_changed() {
+        Some(EditKind::Inspector)
+    } else if camera_moved {
+        Some(EditKind::Camera)
+    } else {
+        None
+    };
+    if let Some(kind) = kind {
+        // An edit of the other kind is finished, so make it a step as it was last update, before this one. Only the
+        // camera moved, or only the rest of the scene was edited, since the last step, so the rest is unchanged
+        if let (Some((pending_kind, _)), Some(camera)) = (history.pending, last_camera) {
+            if pending_kind != kind {
+                let before = match (kind, &history.current) {
+                    (EditKind::Camera, _) => Some(SceneSnapshot {
+                        camera,
+                        ..SceneSnapshot::from(&*inspector_mat)
+                    }),
+                    (EditKind::Inspector, Some(current)) => Some(SceneSnapshot {
+                        camera,
+                        ..current.clone()
+                    }),
+                    (EditKind::Inspector, None) => None,
+                };
+                if let Some(before) = before {
+                    history.commit(before);
+                }
+            }
+        }
+
+        history.pending = Some((kind, now));
+    }
+
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let step_pressed =
+        ctrl && keys.just_pressed(UNDO_KEY) && !egui_contexts.ctx_mut().wants_keyboard_input();
+
+    // Undoing straight after an edit undoes that edit, rather than the one before it
+    let settled = history.pending.is_some_and(|(_, since)| {
+        step_pressed
+            || (mouse_buttons.get_pressed().next().is_none() && now - since >= COALESCE_SECONDS)
+    });
+    if settled {
+        history.commit(SceneSnapshot::from(&*inspector_mat));
+    }
+
+    if step_pressed {
+        if let Some(snapshot) = history.step(!shift) {
+            snapshot.apply(&mut inspector_mat);
+        }
+    }
+}
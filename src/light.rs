@@ -4,14 +4,33 @@ use bevy::{
 };
 use bevy_inspector_egui::InspectorOptions;
 
+#[derive(Debug, Copy, Clone, Default, Reflect, PartialEq, Eq)]
+pub enum ShadingModel {
+    #[default]
+    Phong,
+    // Physically based, with GGX specular and Lambert or Burley diffuse
+    PbrLambert,
+    PbrBurley,
+}
+
+#[derive(Debug, Copy, Clone, Default, Reflect, PartialEq, Eq)]
+pub enum LightType {
+    #[default]
+    Point,
+    // Shines from the sky's sun direction, ignoring the position
+    Directional,
+}
+
 #[derive(Debug, AsBindGroup, Clone, Asset, TypePath, ShaderType, Default)]
 pub struct ShaderLight {
     pub pos: Vec3,
     pub colour: Vec3,
+    pub light_type: u32,
 }
 
 #[derive(Debug, Copy, Clone, Asset, Reflect, Resource, InspectorOptions, Component, Default)]
 pub struct ShaderLightInspector {
+    pub light_type: LightType,
     pub pos: Vec3,
     pub colour: Vec3,
 }
@@ -21,6 +40,7 @@ impl From<ShaderLightInspector> for ShaderLight {
         Self {
             pos: shader_light.pos,
             colour: shader_light.colour,
+            light_type: shader_light.light_type.into(),
         }
     }
 }
@@ -28,8 +48,47 @@ impl From<ShaderLightInspector> for ShaderLight {
 impl From<ShaderLight> for ShaderLightInspector {
     fn from(shader_light: ShaderLight) -> Self {
         Self {
+            light_type: shader_light.light_type.into(),
             pos: shader_light.pos,
             colour: shader_light.colour,
         }
     }
 }
+
+impl From<LightType> for u32 {
+    fn from(light_type: LightType) -> Self {
+        match light_type {
+            LightType::Point => 0,
+            LightType::Directional => 1,
+        }
+    }
+}
+
+impl From<u32> for LightType {
+    fn from(light_type: u32) -> Self {
+        match light_type {
+            1 => Self::Directional,
+            _ => Self::Point,
+        }
+    }
+}
+
+impl From<ShadingModel> for u32 {
+    fn from(shading_model: ShadingModel) -> Self {
+        match shading_model {
+            ShadingModel::Phong => 0,
+            ShadingModel::PbrLambert => 1,
+            ShadingModel::PbrBurley => 2,
+        }
+    }
+}
+
+impl From<u32> for ShadingModel {
+    fn from(shading_model: u32) -> Self {
+        match shading_model {
+            1 => Self::PbrLambert,
+            2 => Self::PbrBurley,
+            _ => Self::Phong,
+        }
+    }
+}
@@ -5,11 +5,28 @@ use bevy_screen_diagnostics::{
 use shader_material::ShaderMatPlugin;
 
 pub mod camera_controller;
+pub mod capture;
+pub mod clock;
+pub mod debug;
+pub mod fog;
 pub mod fullscreen_shader;
+pub mod gizmo;
+pub mod history;
 pub mod light;
+pub mod materials;
+pub mod modifiers;
+pub mod noise;
+pub mod outline;
+pub mod outliner;
+pub mod physics;
+pub mod picking;
+pub mod sdf;
 pub mod shader_loader;
 pub mod shader_material;
 pub mod shape;
+pub mod sky;
+pub mod stereo;
+pub mod tonemapping;
 
 pub fn main() {
     App::new()
@@ -0,0 +1,155 @@
+use bevy::{prelude::*, render::render_resource::ShaderType};
+use bevy_inspector_egui::{prelude::ReflectInspectorOptions, InspectorOptions};
+
+// How many image textures the shader has bindings for
+pub const TEXTURE_SLOTS: usize = 2;
+
+#[derive(Debug, Copy, Clone, Default, Reflect, PartialEq, Eq)]
+pub enum MaterialPattern {
+    #[default]
+    None,
+    Checker,
+    Stripes,
+    Noise,
+    Grid,
+    Gradient,
+}
+
+#[derive(Debug, Copy, Clone, Default, Reflect, PartialEq, Eq)]
+pub enum MaterialTexture {
+    #[default]
+    None,
+    First,
+    Second,
+}
+
+// A pattern and image texture drawn over a shape's colour, projected along each axis in the shape's own space, and
+// the metallic and roughness used by physically based shading
+#[derive(Debug, Copy, Clone, ShaderType)]
+pub struct ShapeMaterial {
+    pub pattern_colour: Vec3,
+    pub pattern: u32,
+    pub pattern_scale: f32,
+    pub texture: u32,
+    pub texture_scale: f32,
+    pub metallic: f32,
+    pub roughness: f32,
+}
+
+#[derive(Debug, Copy, Clone, Reflect, InspectorOptions)]
+#[reflect(InspectorOptions)]
+pub struct ShapeMaterialInspector {
+    pub pattern: MaterialPattern,
+    pub pattern_colour: Vec3,
+    // The size of one repeat of the pattern
+    #[inspector(min = 0.01)]
+    pub pattern_scale: f32,
+    // Which of the texture paths in the ShaderMatInspector to use
+    pub texture: MaterialTexture,
+    #[inspector(min = 0.01)]
+    pub texture_scale: f32,
+    // Only used by the physically based shading models
+    #[inspector(min = 0., max = 1.)]
+    pub metallic: f32,
+    #[inspector(min = 0., max = 1.)]
+    pub roughness: f32,
+}
+
+impl Default for ShapeMaterial {
+    fn default() -> Self {
+        Self {
+            pattern_colour: Vec3::ONE,
+            pattern: MaterialPattern::None.into(),
+            pattern_scale: 0.5,
+            texture: MaterialTexture::None.into(),
+            texture_scale: 1.,
+            metallic: 0.,
+            roughness: 0.5,
+        }
+    }
+}
+
+impl Default for ShapeMaterialInspector {
+    fn default() -> Self {
+        ShapeMaterial::default().into()
+    }
+}
+
+impl From<MaterialPattern> for u32 {
+    fn from(pattern: MaterialPattern) -> Self {
+        match pattern {
+            MaterialPattern::None => 0,
+            MaterialPattern::Checker => 1,
+            MaterialPattern::Stripes => 2,
+            MaterialPattern::Noise => 3,
+            MaterialPattern::Grid => 4,
+            MaterialPattern::Gradient => 5,
+        }
+    }
+}
+
+impl From<u32> for MaterialPattern {
+    fn from(pattern: u32) -> Self {
+        match pattern {
+            1 => Self::Checker,
+            2 => Self::Stripes,
+            3 => Self::Noise,
+            4 => Self::Grid,
+            5 => Self::Gradient,
+            _ => Self::None,
+        }
+    }
+}
+
+impl From<MaterialTexture> for u32 {
+    fn from(texture: MaterialTexture) -> Self {
+        match texture {
+            MaterialTexture::None => 0,
+            MaterialTexture::First => 1,
+            MaterialTexture::Second => 2,
+        }
+    }
+}
+
+impl From<u32> for MaterialTexture {
+    fn from(texture: u32) -> Self {
+        match texture {
+            1 => Self::First,
+            2 => Self::Second,
+            _ => Self::None,
+        }
+    }
+}
+
+impl From<ShapeMaterialInspector> for ShapeMaterial {
+    fn from(inspector: ShapeMaterialInspector) -> Self {
+        Self {
+            pattern_colour: inspector.pattern_colour,
+            pattern: inspector.pattern.into(),
+            pattern_scale: inspector.pattern_scale,
+            texture: inspector.texture.into(),
+            texture_scale: inspector.texture_scale,
+            metallic: inspector.metallic,
+            roughness: inspector.roughness,
+        }
+    }
+}
+
+impl From<ShapeMaterial> for ShapeMaterialInspector {
+    fn from(material: ShapeMaterial) -> Self {
+        Self {
+            pattern: material.pattern.into(),
+            pattern_colour: material.pattern_colour,
+            pattern_scale: material.pattern_scale,
+            texture: material.texture.into(),
+            texture_scale: material.texture_scale,
+            metallic: material.metallic,
+            roughness: material.roughness,
+        }
+    }
+}
+
+// Load the image at a path in the assets folder, an empty path leaves the slot empty
+pub fn load_texture(asset_server: &AssetServer, path: &str) -> Option<Handle<Image>> {
+    (!path.is_empty()).then(|| asset_server.load(path.to_owned()))
+}
@@ -0,0 +1,225 @@
+use bevy::{prelude::*, render::render_resource::ShaderType};
+use bevy_inspector_egui::{prelude::ReflectInspectorOptions, InspectorOptions};
+
+#[derive(Debug, Copy, Clone, Default, Reflect, PartialEq, Eq)]
+pub enum DomainRepetition {
+    #[default]
+    None,
+    Infinite,
+    Limited,
+    Polar,
+}
+
+#[derive(Debug, Copy, Clone, Default, Reflect, PartialEq, Eq)]
+pub enum DomainDeform {
+    #[default]
+    None,
+    Twist,
+    Bend,
+}
+
+#[derive(Debug, Copy, Clone, Default, Reflect, PartialEq, Eq)]
+pub enum DisplacementType {
+    #[default]
+    None,
+    Perlin,
+    Simplex,
+    Fbm,
+    Voronoi,
+    Sine,
+}
+
+// Operators applied to the sample point before a shape's SDF, so one shape can be repeated, mirrored or deformed
+#[derive(Debug, Copy, Clone, ShaderType, Default)]
+pub struct ShapeDomain {
+    pub repetition: u32,
+    pub period: Vec3,
+    pub limit: Vec3,
+    pub polar_count: u32,
+    pub polar_radius: f32,
+    // Bitmask of the axes to mirror across (x = 1, y = 2, z = 4)
+    pub mirror: u32,
+    pub deform: u32,
+    pub deform_amount: f32,
+}
+
+#[derive(Debug, Copy, Clone, Reflect, InspectorOptions, Default)]
+#[reflect(InspectorOptions)]
+pub struct ShapeDomainInspector {
+    pub repetition: DomainRepetition,
+    // The spacing between copies along each axis, axes with a period of zero aren't repeated
+    pub period: Vec3,
+    // How many copies there are either side of the original when the repetition is limited
+    pub limit: UVec3,
+    // Copies around the Y axis, 0 leaves the shape where it is
+    #[inspector(min = 0)]
+    pub polar_count: u32,
+    pub polar_radius: f32,
+    pub mirror_x: bool,
+    pub mirror_y: bool,
+    pub mirror_z: bool,
+    pub deform: DomainDeform,
+    pub deform_amount: f32,
+}
+
+// Noise added to a shape's distance to give it a rough or rippled surface
+#[derive(Debug, Copy, Clone, ShaderType)]
+pub struct ShapeDisplacement {
+    pub displacement_type: u32,
+    pub amplitude: f32,
+    pub frequency: f32,
+    pub octaves: u32,
+}
+
+#[derive(Debug, Copy, Clone, Reflect, InspectorOptions)]
+#[reflect(InspectorOptions)]
+pub struct ShapeDisplacementInspector {
+    pub displacement_type: DisplacementType,
+    pub amplitude: f32,
+    #[inspector(min = 0.)]
+    pub frequency: f32,
+    // Only used by fBm
+    #[inspector(min = 1, max = 8)]
+    pub octaves: u32,
+}
+
+impl Default for ShapeDisplacement {
+    fn default() -> Self {
+        Self {
+            displacement_type: DisplacementType::None.into(),
+            amplitude: 0.1,
+            frequency: 4.,
+            octaves: 3,
+        }
+    }
+}
+
+impl Default for ShapeDisplacementInspector {
+    fn default() -> Self {
+        ShapeDisplacement::default().into()
+    }
+}
+
+impl From<DomainRepetition> for u32 {
+    fn from(repetition: DomainRepetition) -> Self {
+        match repetition {
+            DomainRepetition::None => 0,
+            DomainRepetition::Infinite => 1,
+            DomainRepetition::Limited => 2,
+            DomainRepetition::Polar => 3,
+        }
+    }
+}
+
+impl From<u32> for DomainRepetition {
+    fn from(repetition: u32) -> Self {
+        match repetition {
+            1 => Self::Infinite,
+            2 => Self::Limited,
+            3 => Self::Polar,
+            _ => Self::None,
+        }
+    }
+}
+
+impl From<DomainDeform> for u32 {
+    fn from(deform: DomainDeform) -> Self {
+        match deform {
+            DomainDeform::None => 0,
+            DomainDeform::Twist => 1,
+            DomainDeform::Bend => 2,
+        }
+    }
+}
+
+impl From<u32> for DomainDeform {
+    fn from(deform: u32) -> Self {
+        match deform {
+            1 => Self::Twist,
+            2 => Self::Bend,
+            _ => Self::None,
+        }
+    }
+}
+
+impl From<DisplacementType> for u32 {
+    fn from(displacement_type: DisplacementType) -> Self {
+        match displacement_type {
+            DisplacementType::None => 0,
+            DisplacementType::Perlin => 1,
+            DisplacementType::Simplex => 2,
+            DisplacementType::Fbm => 3,
+            DisplacementType::Voronoi => 4,
+            DisplacementType::Sine => 5,
+        }
+    }
+}
+
+impl From<u32> for DisplacementType {
+    fn from(displacement_type: u32) -> Self {
+        match displacement_type {
+            1 => Self::Perlin,
+            2 => Self::Simplex,
+            3 => Self::Fbm,
+            4 => Self::Voronoi,
+            5 => Self::Sine,
+            _ => Self::None,
+        }
+    }
+}
+
+impl From<ShapeDomainInspector> for ShapeDomain {
+    fn from(inspector: ShapeDomainInspector) -> Self {
+        Self {
+            repetition: inspector.repetition.into(),
+            period: inspector.period,
+            limit: inspector.limit.as_vec3(),
+            polar_count: inspector.polar_count,
+            polar_radius: inspector.polar_radius,
+            mirror: inspector.mirror_x as u32
+                | (inspector.mirror_y as u32) << 1
+                | (inspector.mirror_z as u32) << 2,
+            deform: inspector.deform.into(),
+            deform_amount: inspector.deform_amount,
+        }
+    }
+}
+
+impl From<ShapeDomain> for ShapeDomainInspector {
+    fn from(domain: ShapeDomain) -> Self {
+        Self {
+            repetition: domain.repetition.into(),
+            period: domain.period,
+            limit: domain.limit.as_uvec3(),
+            polar_count: domain.polar_count,
+            polar_radius: domain.polar_radius,
+            mirror_x: domain.mirror & 1 != 0,
+            mirror_y: domain.mirror & 2 != 0,
+            mirror_z: domain.mirror & 4 != 0,
+            deform: domain.deform.into(),
+            deform_amount: domain.deform_amount,
+        }
+    }
+}
+
+impl From<ShapeDisplacementInspector> for ShapeDisplacement {
+    fn from(inspector: ShapeDisplacementInspector) -> Self {
+        Self {
+            displacement_type: inspector.displacement_type.into(),
+            amplitude: inspector.amplitude,
+            frequency: inspector.frequency,
+            octaves: inspector.octaves,
+        }
+    }
+}
+
+impl From<ShapeDisplacement> for ShapeDisplacementInspector {
+    fn from(displacement: ShapeDisplacement) -> Self {
+        Self {
+            displacement_type: displacement.displacement_type.into(),
+            amplitude: displacement.amplitude,
+            frequency: displacement.frequency,
+            octaves: displacement.octaves,
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::cell::RefCell;
+
+use bevy::prelude::*;
+use bracket_noise::prelude::{FastNoise, FractalType, NoiseType};
+use bracket_random::prelude::RandomNumberGenerator;
+
+// The seed of the noise used by the shader and the CPU
+pub const NOISE_SEED: u64 = 1337;
+
+thread_local! {
+    static NOISE: RefCell<FastNoise> = RefCell::new(create_noise());
+}
+
+fn create_noise() -> FastNoise {
+    let mut noise = FastNoise::seeded(NOISE_SEED);
+    noise.set_frequency(1.);
+
+    noise
+}
+
+// FastNoise's permutation table, generated in the same way as FastNoise::set_seed so the shader's noise matches the CPU's,
+// the second half repeats the first so lookups never need to wrap
+pub fn permutation_table(seed: u64) -> Vec<u32> {
+    let mut rng = RandomNumberGenerator::seeded(seed);
+    let mut perm = (0..512).map(|i| i & 255).collect::<Vec<u32>>();
+
+    for j in 0..256 {
+        let k = (rng.next_u64() % (256 - j) + j) as usize;
+
+        perm.swap(j as usize, k);
+        perm[j as usize + 256] = perm[j as usize];
+    }
+
+    perm
+}
+
+// CPU versions of the noise in noise.wgsl
+
+pub fn perlin_fbm_2d(p: Vec2, octaves: u32, lacunarity: f32, gain: f32) -> f32 {
+    NOISE.with_borrow_mut(|noise| {
+        noise.set_noise_type(NoiseType::PerlinFractal);
+        noise.set_fractal_type(FractalType::FBM);
+        noise.set_fractal_octaves(octaves as i32);
+        noise.set_fractal_lacunarity(lacunarity);
+        noise.set_fractal_gain(gain);
+        noise.get_noise(p.x, p.y)
+    })
+}
+
+pub fn perlin_3d(p: Vec3) -> f32 {
+    NOISE.with_borrow_mut(|noise| {
+        noise.set_noise_type(NoiseType::Perlin);
+        noise.get_noise3d(p.x, p.y, p.z)
+    })
+}
+
+pub fn perlin_fbm_3d(p: Vec3, octaves: u32, lacunarity: f32, gain: f32) -> f32 {
+    NOISE.with_borrow_mut(|noise| {
+        noise.set_noise_type(NoiseType::PerlinFractal);
+        noise.set_fractal_type(FractalType::FBM);
+        noise.set_fractal_octaves(octaves as i32);
+        noise.set_fractal_lacunarity(lacunarity);
+        noise.set_fractal_gain(gain);
+        noise.get_noise3d(p.x, p.y, p.z)
+    })
+}
+
+pub fn simplex_3d(p: Vec3) -> f32 {
+    NOISE.with_borrow_mut(|noise| {
+        noise.set_noise_type(NoiseType::Simplex);
+        noise.get_noise3d(p.x, p.y, p.z)
+    })
+}
+
+// Distance to the nearest of a grid of randomly offset points, remapped to roughly -1 to 1 like the other noise
+pub fn voronoi_3d(p: Vec3) -> f32 {
+    let cell = p.floor().as_ivec3();
+
+    NOISE.with_borrow(|noise| {
+        let mut min_dist_sq: f32 = 3.;
+
+        for z in -1..=1 {
+            for y in -1..=1 {
+                for x in -1..=1 {
+                    let neighbour = cell + IVec3::new(x, y, z);
+                    let jitter = Vec3::new(
+                        noise.index3d_256(0, neighbour.x, neighbour.y, neighbour.z) as f32,
+                        noise.index3d_256(1, neighbour.x, neighbour.y, neighbour.z) as f32,
+                        noise.index3d_256(2, neighbour.x, neighbour.y, neighbour.z) as f32,
+                    ) / 255.;
+
+                    let offset = neighbour.as_vec3() + jitter - p;
+                    min_dist_sq = min_dist_sq.min(offset.length_squared());
+                }
+            }
+        }
+
+        min_dist_sq.sqrt() * 2. - 1.
+    })
+}
+
+pub fn fractal_bounding(octaves: u32, gain: f32) -> f32 {
+    let mut amp = gain;
+    let mut amp_fractal = 1.;
+
+    for _ in 0..octaves {
+        amp_fractal += amp;
+        amp *= gain;
+    }
+
+    1. / amp_fractal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Values from FastNoise with NOISE_SEED, the shader's noise.wgsl is written to give the same ones
+    #[test]
+    fn perlin_fbm_2d_matches_known_values() {
+        let known = [
+            (Vec2::new(0.3, 0.7), 1, 2., 0.5, -0.127_676),
+            (Vec2::new(0.3, 0.7), 5, 2., 0.5, -0.171_126_4),
+            (Vec2::new(0.3, 0.7), 3, 2.5, 0.4, -0.120_140_4),
+            (Vec2::new(-1.25, 2.5), 1, 2., 0.5, 0.321_777_3),
+            (Vec2::new(-1.25, 2.5), 5, 2., 0.5, 0.181_671_6),
+            (Vec2::new(-1.25, 2.5), 3, 2.5, 0.4, 0.343_922),
+            (Vec2::new(10.1, -3.6), 1, 2., 0.5, -0.031_296_4),
+            (Vec2::new(10.1, -3.6), 5, 2., 0.5, 0.018_363_2),
+            (Vec2::new(10.1, -3.6), 3, 2.5, 0.4, -0.145_939_5),
+        ];
+
+        for (p, octaves, lacunarity, gain, expected) in known {
+            let noise = perlin_fbm_2d(p, octaves, lacunarity, gain);
+            assert!(
+                (noise - expected).abs() < 1e-6,
+                "{octaves} octaves at {p} gave {noise}, not {expected}"
+            );
+        }
+    }
+
+    // Perlin noise is zero on its lattice, and doubling the frequency keeps lattice points on the lattice
+    #[test]
+    fn perlin_fbm_2d_is_zero_on_the_lattice() {
+        for p in [Vec2::ZERO, Vec2::new(3., -2.), Vec2::new(-17., 40.)] {
+            assert_eq!(perlin_fbm_2d(p, 5, 2., 0.5), 0.);
+        }
+        assert_eq!(perlin_fbm_2d(Vec2::splat(0.5), 5, 2., 0.5), 0.);
+    }
+}
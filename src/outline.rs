@@ -0,0 +1,84 @@
+use bevy::{prelude::*, render::render_resource::ShaderType};
+use bevy_inspector_egui::{prelude::ReflectInspectorOptions, InspectorOptions};
+
+// Stylised rendering, drawing outlines around shapes and banding their lighting like a cartoon
+#[derive(Debug, Copy, Clone, ShaderType)]
+pub struct ShaderOutline {
+    pub enabled: u32,
+    pub width: f32,
+    pub colour: Vec3,
+    pub object_edges: u32,
+    pub normal_edges: u32,
+    // The cosine of the crease angle
+    pub normal_threshold: f32,
+    pub toon_bands: u32,
+}
+
+#[derive(Debug, Copy, Clone, Reflect, InspectorOptions)]
+#[reflect(InspectorOptions)]
+pub struct ShaderOutlineInspector {
+    // Silhouettes around the shapes against the sky
+    pub enabled: bool,
+    // In pixels
+    #[inspector(min = 0.5, max = 10.)]
+    pub width: f32,
+    pub colour: Vec3,
+    // Also outline where one shape is in front of another, marching extra rays per pixel
+    pub object_edges: bool,
+    // Also outline creases sharper than the crease angle
+    pub normal_edges: bool,
+    // In degrees
+    #[inspector(min = 1., max = 179.)]
+    pub crease_angle: f32,
+    // Quantise the lighting into this many bands, 0 for smooth lighting
+    #[inspector(max = 16)]
+    pub toon_bands: u32,
+}
+
+impl Default for ShaderOutline {
+    fn default() -> Self {
+        Self {
+            enabled: true.into(),
+            width: 2.,
+            colour: Vec3::ONE,
+            object_edges: false.into(),
+            normal_edges: false.into(),
+            normal_threshold: 30_f32.to_radians().cos(),
+            toon_bands: 0,
+        }
+    }
+}
+
+impl Default for ShaderOutlineInspector {
+    fn default() -> Self {
+        ShaderOutline::default().into()
+    }
+}
+
+impl From<ShaderOutlineInspector> for ShaderOutline {
+    fn from(inspector: ShaderOutlineInspector) -> Self {
+        Self {
+            enabled: inspector.enabled.into(),
+            width: inspector.width,
+            colour: inspector.colour,
+            object_edges: inspector.object_edges.into(),
+            normal_edges: inspector.normal_edges.into(),
+            normal_threshold: inspector.crease_angle.to_radians().cos(),
+            toon_bands: inspector.toon_bands,
+        }
+    }
+}
+
+impl From<ShaderOutline> for ShaderOutlineInspector {
+    fn from(outline: ShaderOutline) -> Self {
+        Self {
+            enabled: outline.enabled != 0,
+            width: outline.width,
+            colour: outline.colour,
+            object_edges: outline.object_edges != 0,
+            normal_edges: outline.normal_edges != 0,
+            crease_angle: outline.normal_threshold.clamp(-1., 1.).acos().to_degrees(),
+            toon_bands: outline.toon_bands,
+        }
+    }
+}
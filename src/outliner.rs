@@ -0,0 +1,381 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    fullscreen_shader::FullscreenCover,
+    picking::{ShapePickingSet, ShapeSelection},
+    shader_material::{ShaderMat, ShaderMatInspector},
+    shape::{ShapeInspector, ShapeType},
+};
+
+// How far in front of the camera new shapes are added
+pub const NEW_SHAPE_DISTANCE: f32 = 6.;
+
+const SHAPE_TEMPLATES: [ShapeType; 9] = [
+    ShapeType::Sphere,
+    ShapeType::Cube,
+    ShapeType::Plane,
+    ShapeType::Portal,
+    ShapeType::Mandelbulb,
+    ShapeType::MengerSponge,
+    ShapeType::SierpinskiTetrahedron,
+    ShapeType::Mandelbox,
+    ShapeType::Terrain,
+];
+
+pub struct OutlinerPlugin;
+
+impl Plugin for OutlinerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, show_outliner.before(ShapePickingSet));
+    }
+}
+
+#[derive(Debug, Clone, Default, Reflect, PartialEq)]
+pub struct ShapeGroup {
+    pub name: String,
+    // Hides or locks every shape in the group, on top of the shapes' own settings
+    pub hidden: bool,
+    pub locked: bool,
+}
+
+#[derive(Debug, Clone)]
+enum OutlinerAction {
+    Add(ShapeType),
+    Duplicate(usize),
+    Delete(usize),
+    // Move the shape at the first index to before the shape at the second, joining its group
+    Move(usize, usize),
+    Select(usize),
+    ToggleHidden(usize),
+    ToggleLocked(usize),
+    SetGroup(usize, Option<usize>),
+    AddGroup,
+    DeleteGroup(usize),
+    RenameGroup(usize, String),
+    ToggleGroupHidden(usize),
+    ToggleGroupLocked(usize),
+}
+
+// The list of shapes, grouped, with buttons to add, remove and rearrange them. Shapes are dragged by their handle onto
+// another shape to move them there, or onto a group to add them to it
+fn show_outliner(
+    mut egui_contexts: EguiContexts,
+    cover: Query<&Handle<ShaderMat>, With<FullscreenCover>>,
+    shader_mats: Res<Assets<ShaderMat>>,
+    mut selection: ResMut<ShapeSelection>,
+    mut inspector_mat: ResMut<ShaderMatInspector>,
+) {
+    let mut actions = Vec::new();
+
+    egui::Window::new("Outliner").show(egui_contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.menu_button("Add shape", |ui| {
+                for shape_type in SHAPE_TEMPLATES {
+                    if ui.button(format!("{shape_type:?}")).clicked() {
+                        actions.push(OutlinerAction::Add(shape_type));
+                        ui.close_menu();
+                    }
+                }
+            });
+
+            if ui.button("Add group").clicked() {
+                actions.push(OutlinerAction::AddGroup);
+            }
+        });
+
+        ui.separator();
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            let groups = &inspector_mat.groups;
+            let group_of =
+                |shape: &ShapeInspector| shape.group.filter(|&group| group < groups.len());
+
+            for (i, shape) in inspector_mat.shapes.iter().enumerate() {
+                if group_of(shape).is_none() {
+                    shape_row(ui, i, shape, groups, selection.selected, &mut actions);
+                }
+            }
+
+            for (group_index, group) in groups.iter().enumerate() {
+                ui.separator();
+
+                let header = ui.horizontal(|ui| {
+                    let mut name = group.name.clone();
+                    if ui.text_edit_singleline(&mut name).changed() {
+                        actions.push(OutlinerAction::RenameGroup(group_index, name));
+                    }
+                    if ui.selectable_label(group.hidden, "hide").clicked() {
+                        actions.push(OutlinerAction::ToggleGroupHidden(group_index));
+                    }
+                    if ui.selectable_label(group.locked, "lock").clicked() {
+                        actions.push(OutlinerAction::ToggleGroupLocked(group_index));
+                    }
+                    if ui.button("ungroup").clicked() {
+                        actions.push(OutlinerAction::DeleteGroup(group_index));
+                    }
+                });
+
+                if let Some(dragged) = header.response.dnd_release_payload::<usize>() {
+                    actions.push(OutlinerAction::SetGroup(*dragged, Some(group_index)));
+                }
+
+                ui.indent(group_index, |ui| {
+                    for (i, shape) in inspector_mat.shapes.iter().enumerate() {
+                        if group_of(shape) == Some(group_index) {
+                            shape_row(ui, i, shape, groups, selection.selected, &mut actions);
+                        }
+                    }
+                });
+            }
+        });
+    });
+
+    if actions.is_empty() {
+        return;
+    }
+
+    // New shapes go in front of the on screen camera
+    let spawn_pos = cover
+        .get_single()
+        .ok()
+        .and_then(|handle| shader_mats.get(handle))
+        .map_or(Vec3::ZERO, |mat| {
+            mat.camera.pos + mat.camera.forward * NEW_SHAPE_DISTANCE
+        });
+
+    let mut selected = selection.selected;
+    for action in actions {
+        apply_action(action, &mut inspector_mat, &mut selected, spawn_pos);
+    }
+
+    // Only touch the selection when it moves, otherwise it would copy its old settings back over the shape
+    if selected != selection.selected {
+        selection.selected = selected;
+        selection.light_selected = false;
+    }
+}
+
+fn shape_row(
+    ui: &mut egui::Ui,
+    index: usize,
+    shape: &ShapeInspector,
+    groups: &[ShapeGroup],
+    selected: Option<usize>,
+    actions: &mut Vec<OutlinerAction>,
+) {
+    let row = ui.horizontal(|ui| {
+        ui.dnd_drag_source(egui::Id::new(("outliner_shape", shape.id)), index, |ui| {
+            ui.label("::");
+        });
+
+        let label = format!("{} ({:?})", shape.name, shape.shape_type);
+        if ui
+            .selectable_label(selected == Some(index), label)
+            .clicked()
+        {
+            actions.push(OutlinerAction::Select(index));
+        }
+        if ui.selectable_label(shape.hidden, "hide").clicked() {
+            actions.push(OutlinerAction::ToggleHidden(index));
+        }
+        if ui.selectable_label(shape.locked, "lock").clicked() {
+            actions.push(OutlinerAction::ToggleLocked(index));
+        }
+
+        let group_name = |group: Option<usize>| {
+            group
+                .and_then(|group| groups.get(group))
+                .map_or("No group".to_owned(), |group| group.name.clone())
+        };
+        egui::ComboBox::from_id_source(("outliner_group", shape.id))
+            .selected_text(group_name(shape.group))
+            .show_ui(ui, |ui| {
+                for group in std::iter::once(None).chain((0..groups.len()).map(Some)) {
+                    if ui
+                        .selectable_label(shape.group == group, group_name(group))
+                        .clicked()
+                    {
+                        actions.push(OutlinerAction::SetGroup(index, group));
+                    }
+                }
+            });
+
+        if ui.button("duplicate").clicked() {
+            actions.push(OutlinerAction::Duplicate(index));
+        }
+        if ui.button("delete").clicked() {
+            actions.push(OutlinerAction::Delete(index));
+        }
+    });
+
+    if let Some(dragged) = row.response.dnd_release_payload::<usize>() {
+        if *dragged != index {
+            actions.push(OutlinerAction::Move(*dragged, index));
+        }
+    }
+}
+
+// Change the shapes, keeping the selection on the same shape as the indices move
+fn apply_action(
+    action: OutlinerAction,
+    inspector_mat: &mut ShaderMatInspector,
+    selected: &mut Option<usize>,
+    spawn_pos: Vec3,
+) {
+    let shapes_len = inspector_mat.shapes.len();
+    let groups_len = inspector_mat.groups.len();
+
+    match action {
+        OutlinerAction::Add(shape_type) => {
+            let id = inspector_mat.next_shape_id();
+            inspector_mat
+                .shapes
+                .push(ShapeInspector::template(shape_type, id, spawn_pos));
+            *selected = Some(shapes_len);
+        }
+        OutlinerAction::Duplicate(index) if index < shapes_len => {
+            let id = inspector_mat.next_shape_id();
+            let shape = &inspector_mat.shapes[index];
+            let copy = ShapeInspector {
+                id,
+                name: format!("{} copy", shape.name),
+                ..shape.clone()
+            };
+            inspector_mat.shapes.insert(index + 1, copy);
+            *selected = Some(index + 1);
+        }
+        OutlinerAction::Delete(index) if index < shapes_len => {
+            inspector_mat.shapes.remove(index);
+            *selected = match *selected {
+                Some(s) if s == index => None,
+                Some(s) if s > index => Some(s - 1),
+                s => s,
+            };
+        }
+        OutlinerAction::Move(from, to) if from < shapes_len && to < shapes_len => {
+            // Read before the shape is taken out, which moves the target down one when it comes after
+            let group = inspector_mat.shapes[to].group;
+            let mut shape = inspector_mat.shapes.remove(from);
+            shape.group = group;
+
+            // Removing the shape moves the ones after it down one
+            let to = if from < to { to - 1 } else { to };
+            inspector_mat.shapes.insert(to, shape);
+
+            *selected = selected.map(|s| {
+                if s == from {
+                    to
+                } else {
+                    let s = s - (from < s) as usize;
+                    s + (s >= to) as usize
+                }
+            });
+        }
+        OutlinerAction::Select(index) => *selected = Some(index),
+        OutlinerAction::ToggleHidden(index) if index < shapes_len => {
+            inspector_mat.shapes[index].hidden ^= true;
+        }
+        OutlinerAction::ToggleLocked(index) if index < shapes_len => {
+            inspector_mat.shapes[index].locked ^= true;
+        }
+        OutlinerAction::SetGroup(index, group) if index < shapes_len => {
+            inspector_mat.shapes[index].group = group;
+        }
+        OutlinerAction::AddGroup => inspector_mat.groups.push(ShapeGroup {
+            name: format!("Group {}", groups_len + 1),
+            ..default()
+        }),
+        OutlinerAction::DeleteGroup(group) if group < groups_len => {
+            inspector_mat.groups.remove(group);
+
+            // The group's shapes are kept, and the groups after it move down one
+            for shape in inspector_mat.shapes.iter_mut() {
+                shape.group = match shape.group {
+                    Some(g) if g == group => None,
+                    Some(g) if g > group => Some(g - 1),
+                    g => g,
+                };
+            }
+        }
+        OutlinerAction::RenameGroup(group, name) if group < groups_len => {
+            inspector_mat.groups[group].name = name;
+        }
+        OutlinerAction::ToggleGroupHidden(group) if group < groups_len => {
+            inspector_mat.groups[group].hidden ^= true;
+        }
+        OutlinerAction::ToggleGroupLocked(group) if group < groups_len => {
+            inspector_mat.groups[group].locked ^= true;
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::ShapeId;
+
+    // Four spheres with IDs 0 to 3, where only the last is in a group
+    fn four_shapes() -> ShaderMatInspector {
+        let mut shapes: Vec<_> = (0..4)
+            .map(|id| ShapeInspector::template(ShapeType::Sphere, ShapeId(id), Vec3::ZERO))
+            .collect();
+        shapes[3].group = Some(0);
+
+        ShaderMatInspector {
+            shapes,
+            groups: vec![ShapeGroup::default()],
+            ..default()
+        }
+    }
+
+    fn ids(inspector_mat: &ShaderMatInspector) -> Vec<u32> {
+        inspector_mat
+            .shapes
+            .iter()
+            .map(|shape| shape.id.0)
+            .collect()
+    }
+
+    fn move_shape(from: usize, to: usize, selected: usize) -> (ShaderMatInspector, Option<usize>) {
+        let mut inspector_mat = four_shapes();
+        let mut selected = Some(selected);
+        apply_action(
+            OutlinerAction::Move(from, to),
+            &mut inspector_mat,
+            &mut selected,
+            Vec3::ZERO,
+        );
+
+        (inspector_mat, selected)
+    }
+
+    #[test]
+    fn move_down_goes_before_the_target() {
+        let (inspector_mat, selected) = move_shape(0, 2, 0);
+
+        assert_eq!(ids(&inspector_mat), [1, 0, 2, 3]);
+        assert_eq!(inspector_mat.shapes[1].group, None);
+        assert_eq!(selected, Some(1));
+    }
+
+    #[test]
+    fn move_up_goes_before_the_target() {
+        let (inspector_mat, selected) = move_shape(3, 1, 1);
+
+        assert_eq!(ids(&inspector_mat), [0, 3, 1, 2]);
+        // Joins the target's group, so leaves its own
+        assert_eq!(inspector_mat.shapes[1].group, None);
+        assert_eq!(selected, Some(2));
+    }
+
+    #[test]
+    fn move_onto_the_last_row_joins_its_group() {
+        let (inspector_mat, selected) = move_shape(0, 3, 2);
+
+        assert_eq!(ids(&inspector_mat), [1, 2, 0, 3]);
+        assert_eq!(inspector_mat.shapes[2].group, Some(0));
+        assert_eq!(selected, Some(1));
+    }
+}
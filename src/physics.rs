@@ -0,0 +1,406 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_inspector_egui::{
+    bevy_egui::EguiContexts, prelude::ReflectInspectorOptions, quick::ResourceInspectorPlugin,
+    InspectorOptions,
+};
+
+use crate::{
+    clock::{ShaderClock, ShaderClockSet, CLOCK_MIN_TIMESTEP},
+    sdf::{get_distance, get_normal},
+    shader_material::{update_shadermat_from_egui, ShaderMat, ShaderMatInspector},
+    shape::{Shape, ShapeType},
+};
+
+pub const PHYSICS_GRAVITY: Vec3 = Vec3::new(0., -9.81, 0.);
+// Restarts every dynamic shape from its position in the inspector
+pub const PHYSICS_RESET_KEY: KeyCode = KeyCode::KeyB;
+// Stops a long frame or a jump forward in time from simulating for ages, any time past this many steps is dropped
+const MAX_PHYSICS_STEPS: u32 = 32;
+// Bodies moving slower than this along a contact normal stop bouncing, so they can come to rest
+const REST_SPEED: f32 = 0.5;
+
+pub struct ShapePhysicsPlugin;
+
+impl Plugin for ShapePhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PhysicsSettings::default())
+            .insert_resource(PhysicsBodies::default())
+            .register_type::<PhysicsSettings>()
+            .add_plugins(ResourceInspectorPlugin::<PhysicsSettings>::default())
+            .add_systems(
+                Update,
+                step_physics
+                    .after(ShaderClockSet)
+                    .after(update_shadermat_from_egui),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Resource, Reflect, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
+pub struct PhysicsSettings {
+    pub enabled: bool,
+    pub gravity: Vec3,
+}
+
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            gravity: PHYSICS_GRAVITY,
+        }
+    }
+}
+
+// Makes a sphere or cube a dynamic body, which falls and bounces off the rest of the scene. Bodies move but don't
+// rotate, and start from the shape's position whenever it is changed
+#[derive(Debug, Copy, Clone, Reflect, InspectorOptions)]
+#[reflect(InspectorOptions)]
+pub struct ShapePhysics {
+    pub dynamic: bool,
+    // The velocity the body starts with
+    pub velocity: Vec3,
+    // How much speed is kept when bouncing, 1 bounces forever
+    #[inspector(min = 0., max = 1.)]
+    pub restitution: f32,
+    #[inspector(min = 0., max = 1.)]
+    pub friction: f32,
+}
+
+impl Default for ShapePhysics {
+    fn default() -> Self {
+        Self {
+            dynamic: false,
+            velocity: Vec3::ZERO,
+            restitution: 0.4,
+            friction: 0.3,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct PhysicsBody {
+    pos: Vec3,
+    velocity: Vec3,
+    // The shape's position and velocity in the inspector when the body started, changing them restarts the body
+    start_pos: Vec3,
+    start_velocity: Vec3,
+}
+
+/// The simulated state of the dynamic shapes, by shape ID
+#[derive(Debug, Default, Resource)]
+pub struct PhysicsBodies {
+    bodies: HashMap<u32, PhysicsBody>,
+    last_elapsed: Option<f32>,
+}
+
+fn is_dynamic(shape: &Shape) -> bool {
+    shape.dynamic != 0
+        && shape.hidden == 0
+        && matches!(
+            ShapeType::from(shape.shape_type),
+            ShapeType::Sphere | ShapeType::Cube
+        )
+}
+
+// The centres of spheres covering the body, relative to its centre, and their radius. Cubes are covered by spheres at
+// their corners, edges and faces, as the cube's distance is zero everywhere inside it so points on its surface can't
+// tell how far into another cube they are
+fn contact_points(shape: &Shape) -> (Vec<Vec3>, f32) {
+    match ShapeType::from(shape.shape_type) {
+        ShapeType::Sphere => (vec![Vec3::ZERO], shape.size.x),
+        _ => {
+            let rotation = Quat::from_vec4(shape.rotation);
+            let radius = shape.size.min_element() * 0.5;
+            let points = (0..27)
+                .map(|i| Vec3::new((i % 3) as f32, (i / 3 % 3) as f32, (i / 9) as f32) - 1.)
+                .filter(|offset| *offset != Vec3::ZERO)
+                .map(|offset| rotation * (offset * (shape.size - radius)))
+                .collect();
+
+            (points, radius)
+        }
+    }
+}
+
+// Advance the bodies with the shader clock, in its fixed steps, then move the shapes in the shader to match
+fn step_physics(
+    keys: Res<ButtonInput<KeyCode>>,
+    clock: Res<ShaderClock>,
+    settings: Res<PhysicsSettings>,
+    inspector_mat: Res<ShaderMatInspector>,
+    mut physics: ResMut<PhysicsBodies>,
+    mut shader_mats: ResMut<Assets<ShaderMat>>,
+    mut egui_contexts: EguiContexts,
+) {
+    let shapes = inspector_mat.shader_shapes();
+
+    // Start bodies for new dynamic shapes, and restart any whose shapes were edited
+    // Typing into egui doesn't restart them
+    let reset =
+        keys.just_pressed(PHYSICS_RESET_KEY) && !egui_contexts.ctx_mut().wants_keyboard_input();
+    physics.bodies.retain(|id, _| {
+        shapes
+            .iter()
+            .any(|shape| shape.id == *id && is_dynamic(shape))
+    });
+    for (shape, inspector_shape) in shapes.iter().zip(&inspector_mat.shapes) {
+        if !is_dynamic(shape) {
+            continue;
+        }
+
+        let start_velocity = inspector_shape.physics.velocity;
+        let body = physics.bodies.entry(shape.id).or_insert(PhysicsBody {
+            pos: shape.pos,
+            velocity: start_velocity,
+            start_pos: shape.pos,
+            start_velocity,
+        });
+        if reset || body.start_pos != shape.pos || body.start_velocity != start_velocity {
+            *body = PhysicsBody {
+                pos: shape.pos,
+                velocity: start_velocity,
+                start_pos: shape.pos,
+                start_velocity,
+            };
+        }
+    }
+
+    // Only move forwards, so scrubbing backwards or resetting the clock doesn't launch the bodies
+    let delta = physics
+        .last_elapsed
+        .map_or(0., |last| (clock.elapsed - last).max(0.));
+    physics.last_elapsed = Some(clock.elapsed);
+
+    if settings.enabled {
+        advance_bodies(
+            &mut physics,
+            &shapes,
+            &inspector_mat,
+            settings.gravity,
+            clock.elapsed,
+            delta,
+            clock.fixed_timestep,
+        );
+    }
+
+    if physics.bodies.is_empty() {
+        return;
+    }
+
+    for (_handle, mat) in shader_mats.iter_mut() {
+        for shape in mat.shapes.iter_mut() {
+            if let Some(body) = physics.bodies.get(&shape.id) {
+                shape.pos = body.pos;
+            }
+        }
+    }
+}
+
+// Step the bodies through a delta in fixed steps, so they never move far enough in one step to pass through a shape
+fn advance_bodies(
+    physics: &mut PhysicsBodies,
+    shapes: &[Shape],
+    inspector_mat: &ShaderMatInspector,
+    gravity: Vec3,
+    time: f32,
+    delta: f32,
+    timestep: f32,
+) {
+    let timestep = timestep.max(CLOCK_MIN_TIMESTEP);
+    let steps = ((delta / timestep).round() as u32).min(MAX_PHYSICS_STEPS);
+
+    for _ in 0..steps {
+        step_bodies(physics, shapes, inspector_mat, gravity, time, timestep);
+    }
+}
+
+fn step_bodies(
+    physics: &mut PhysicsBodies,
+    shapes: &[Shape],
+    inspector_mat: &ShaderMatInspector,
+    gravity: Vec3,
+    time: f32,
+    dt: f32,
+) {
+    // Bodies collide with everything else, including other bodies where they were at the start of the step. Portals
+    // aren't solid, and the union type is ignored as intersecting the scene makes no sense to collide with
+    let scene = shapes
+        .iter()
+        .filter(|shape| ShapeType::from(shape.shape_type) != ShapeType::Portal)
+        .map(|shape| Shape {
+            pos: physics
+                .bodies
+                .get(&shape.id)
+                .map_or(shape.pos, |body| body.pos),
+            ..*shape
+        })
+        .collect::<Vec<_>>();
+
+    for (shape, inspector_shape) in shapes.iter().zip(&inspector_mat.shapes) {
+        let Some(body) = physics.bodies.get_mut(&shape.id) else {
+            continue;
+        };
+
+        let others = scene
+            .iter()
+            .filter(|other| other.id != shape.id)
+            .copied()
+            .collect::<Vec<_>>();
+        let distance =
+            |p: Vec3| get_distance(p, &others, 0, inspector_mat.smoothness_val, time).dist;
+
+        body.velocity += gravity * dt;
+        body.pos += body.velocity * dt;
+
+        // Find the point of the body furthest inside the scene
+        let (points, radius) = contact_points(shape);
+        let deepest = points
+            .into_iter()
+            .map(|offset| {
+                let p = body.pos + offset;
+                (p, distance(p) - radius)
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        let Some((contact, penetration)) = deepest.filter(|(_, dist)| *dist < 0.) else {
+            continue;
+        };
+
+        let normal = get_normal(contact, &others, 0, inspector_mat.smoothness_val, time);
+        if normal == Vec3::ZERO {
+            continue;
+        }
+
+        // Push the body out, then bounce off the surface and slow down along it
+        body.pos -= normal * penetration;
+
+        let normal_speed = body.velocity.dot(normal);
+        if normal_speed < 0. {
+            let tangent_velocity = body.velocity - normal * normal_speed;
+            let restitution = if -normal_speed < REST_SPEED {
+                0.
+            } else {
+                inspector_shape.physics.restitution
+            };
+            let bounce = -normal_speed * (1. + restitution);
+
+            // Friction can at most stop the sliding, rather than reverse it
+            let tangent_speed = tangent_velocity.length();
+            let friction = (inspector_shape.physics.friction * bounce).min(tangent_speed);
+
+            body.velocity = normal * (normal_speed + bounce)
+                + tangent_velocity.normalize_or_zero() * (tangent_speed - friction);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::{ShapeId, ShapeInspector};
+
+    const TIMESTEP: f32 = 1. / 120.;
+
+    // A dynamic ball of radius 1 above a thin plane
+    fn ball_over_plane(height: f32) -> (ShaderMatInspector, PhysicsBodies) {
+        let mut ball = ShapeInspector::template(ShapeType::Sphere, ShapeId(0), Vec3::Y * height);
+        ball.physics.dynamic = true;
+        let plane = ShapeInspector::template(ShapeType::Plane, ShapeId(1), Vec3::ZERO);
+
+        let inspector_mat = ShaderMatInspector {
+            shapes: vec![ball, plane],
+            smoothness_val: 0.01,
+            ..default()
+        };
+
+        let mut physics = PhysicsBodies::default();
+        physics.bodies.insert(
+            0,
+            PhysicsBody {
+                pos: Vec3::Y * height,
+                velocity: Vec3::ZERO,
+                start_pos: Vec3::Y * height,
+                start_velocity: Vec3::ZERO,
+            },
+        );
+
+        (inspector_mat, physics)
+    }
+
+    fn simulate(physics: &mut PhysicsBodies, inspector_mat: &ShaderMatInspector, seconds: f32) {
+        let shapes = inspector_mat.shader_shapes();
+        for _ in 0..(seconds / TIMESTEP) as u32 {
+            advance_bodies(
+                physics,
+                &shapes,
+                inspector_mat,
+                PHYSICS_GRAVITY,
+                0.,
+                TIMESTEP,
+                TIMESTEP,
+            );
+        }
+    }
+
+    #[test]
+    fn sphere_comes_to_rest_on_plane() {
+        let (inspector_mat, mut physics) = ball_over_plane(3.);
+
+        simulate(&mut physics, &inspector_mat, 5.);
+
+        // Resting on top of the plane, which is 0.05 thick either side of its centre
+        let body = physics.bodies[&0];
+        assert!((body.pos.y - 1.05).abs() < 0.05, "ball at {}", body.pos);
+        assert!(
+            body.velocity.length() < 0.1,
+            "ball moving at {}",
+            body.velocity
+        );
+    }
+
+    #[test]
+    fn long_delta_does_not_tunnel() {
+        let (inspector_mat, mut physics) = ball_over_plane(3.);
+        let shapes = inspector_mat.shader_shapes();
+
+        // A ten second jump only simulates the steps that fit, rather than a few huge ones
+        advance_bodies(
+            &mut physics,
+            &shapes,
+            &inspector_mat,
+            PHYSICS_GRAVITY,
+            0.,
+            10.,
+            TIMESTEP,
+        );
+        let speed = physics.bodies[&0].velocity.length();
+        let max_speed = PHYSICS_GRAVITY.length() * MAX_PHYSICS_STEPS as f32 * TIMESTEP;
+        assert!((speed - max_speed).abs() < 1e-3, "moving at {speed}");
+
+        simulate(&mut physics, &inspector_mat, 5.);
+        assert!(
+            physics.bodies[&0].pos.y > 1.,
+            "ball at {}",
+            physics.bodies[&0].pos
+        );
+    }
+
+    #[test]
+    fn cube_contact_points_cover_its_corners() {
+        let cube = Shape {
+            shape_type: ShapeType::Cube.into(),
+            size: Vec3::splat(1.),
+            rotation: Quat::IDENTITY.into(),
+            ..default()
+        };
+
+        let (points, radius) = contact_points(&cube);
+        assert_eq!(points.len(), 26);
+        assert_eq!(radius, 0.5);
+        // Each corner sphere reaches the cube's corner along every axis
+        assert!(points
+            .iter()
+            .any(|point| (*point + radius - Vec3::ONE).length() < 1e-5));
+    }
+}
@@ -0,0 +1,192 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_inspector_egui::{
+    bevy_egui::EguiContexts, prelude::ReflectInspectorOptions, quick::ResourceInspectorPlugin,
+    InspectorOptions,
+};
+
+use crate::{
+    fullscreen_shader::FullscreenCover,
+    gizmo::{world_to_screen, GizmoState, LIGHT_MARKER_RADIUS},
+    light::LightType,
+    sdf::{centre_and_scale_uv_positions, get_ray_dir, get_ray_origin, ray_march},
+    shader_material::{ShaderMat, ShaderMatInspector},
+    shape::ShapeInspector,
+};
+
+pub const PICK_BUTTON: MouseButton = MouseButton::Left;
+
+pub struct ShapePickingPlugin;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemSet)]
+pub struct ShapePickingSet;
+
+impl Plugin for ShapePickingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ShapeSelection::default())
+            .register_type::<ShapeSelection>()
+            .add_plugins(ResourceInspectorPlugin::<ShapeSelection>::default())
+            .add_systems(
+                Update,
+                (
+                    pick_shape_under_cursor,
+                    apply_selection_edits.run_if(resource_changed::<ShapeSelection>),
+                    refresh_selection.run_if(resource_changed::<ShaderMatInspector>),
+                    highlight_selection,
+                )
+                    .chain()
+                    .in_set(ShapePickingSet),
+            );
+    }
+}
+
+/// The shape clicked on, whose settings can be edited here rather than finding it in the `ShaderMatInspector`
+#[derive(Debug, Clone, Default, Resource, Reflect, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
+pub struct ShapeSelection {
+    // The index in ShaderMatInspector::shapes
+    pub selected: Option<usize>,
+    // The point light was clicked on rather than a shape, so the gizmo moves it
+    pub light_selected: bool,
+    pub shape: ShapeInspector,
+    #[reflect(ignore)]
+    pub hovered: Option<usize>,
+    // The index the shape was copied from, so changing the selected index doesn't copy it over another shape
+    #[reflect(ignore)]
+    editing: Option<usize>,
+}
+
+// Cast a ray from the cursor through the on screen camera against the CPU versions of the shapes, ignoring the cursor
+// while it is grabbed by the camera, over an inspector or over a gizmo handle
+#[allow(clippy::too_many_arguments)]
+fn pick_shape_under_cursor(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mut egui_contexts: EguiContexts,
+    gizmo_state: Res<GizmoState>,
+    inspector_mat: Res<ShaderMatInspector>,
+    cover: Query<&Handle<ShaderMat>, With<FullscreenCover>>,
+    shader_mats: Res<Assets<ShaderMat>>,
+    mut selection: ResMut<ShapeSelection>,
+) {
+    let (Ok(window), Some(mat)) = (
+        windows.get_single(),
+        cover
+            .get_single()
+            .ok()
+            .and_then(|handle| shader_mats.get(handle)),
+    ) else {
+        return;
+    };
+
+    let cursor = window
+        .cursor_position()
+        .filter(|_| window.cursor.visible && !egui_contexts.ctx_mut().is_pointer_over_area())
+        .filter(|_| !gizmo_state.is_active());
+
+    let hovered = cursor.and_then(|cursor| {
+        let coords = centre_and_scale_uv_positions(cursor, window.size());
+        let ray_dir = get_ray_dir(&mat.camera, coords);
+        let ray_origin = get_ray_origin(&mat.camera, coords, ray_dir);
+        let shapes = &mat.shapes[..(mat.shapes_len as usize).min(mat.shapes.len())];
+
+        ray_march(
+            ray_origin,
+            ray_dir,
+            shapes,
+            mat.union_type,
+            mat.smoothness_val,
+            mat.time,
+        )
+        .map(|(_dist, shape_index)| shape_index)
+        // Locked shapes can still block the ray, but can't be picked
+        .filter(|&shape_index| !inspector_mat.is_shape_locked(shape_index))
+    });
+
+    // Hovering isn't shown in the inspector, so doesn't need to trigger its change detection
+    if selection.hovered != hovered {
+        selection.bypass_change_detection().hovered = hovered;
+    }
+
+    let Some(cursor) = cursor.filter(|_| mouse_buttons.just_pressed(PICK_BUTTON)) else {
+        return;
+    };
+
+    // The point light's marker is drawn over the shapes, so takes priority over them
+    let light_clicked = LightType::from(mat.light.light_type) == LightType::Point
+        && world_to_screen(&mat.camera, mat.light.pos, window.size())
+            .is_some_and(|light| light.distance(cursor) <= LIGHT_MARKER_RADIUS);
+
+    // Clicking the sky clears the selection
+    selection.light_selected = light_clicked;
+    selection.selected = hovered.filter(|_| !light_clicked);
+}
+
+// Copy the selected shape into the selection when it is first selected, after that copy edits back to the inspector
+fn apply_selection_edits(
+    mut selection: ResMut<ShapeSelection>,
+    mut inspector_mat: ResMut<ShaderMatInspector>,
+) {
+    let Some(index) = selection
+        .selected
+        .filter(|&index| index < inspector_mat.shapes.len())
+    else {
+        selection.bypass_change_detection().editing = None;
+        return;
+    };
+
+    if selection.editing != Some(index) {
+        let selection = selection.bypass_change_detection();
+        selection.shape = inspector_mat.shapes[index].clone();
+        selection.editing = Some(index);
+    } else if !selection
+        .shape
+        .reflect_partial_eq(&inspector_mat.shapes[index])
+        .unwrap_or(false)
+    {
+        // Clicking the selected shape again changes the selection without editing the shape
+        inspector_mat.shapes[index] = selection.shape.clone();
+    }
+}
+
+// Keep the selection up to date when the shape is edited in the ShaderMatInspector
+fn refresh_selection(
+    mut selection: ResMut<ShapeSelection>,
+    inspector_mat: Res<ShaderMatInspector>,
+) {
+    let Some(index) = selection.editing else {
+        return;
+    };
+
+    let selection = selection.bypass_change_detection();
+    match inspector_mat.shapes.get(index) {
+        Some(shape) => selection.shape = shape.clone(),
+        None => {
+            selection.selected = None;
+            selection.editing = None;
+        }
+    }
+}
+
+// Tell the on screen shader which shapes to highlight
+fn highlight_selection(
+    selection: Res<ShapeSelection>,
+    cover: Query<&Handle<ShaderMat>, With<FullscreenCover>>,
+    mut shader_mats: ResMut<Assets<ShaderMat>>,
+) {
+    let Ok(handle) = cover.get_single() else {
+        return;
+    };
+
+    let hovered_shape = selection.hovered.map_or(0, |index| index as u32 + 1);
+    let selected_shape = selection.selected.map_or(0, |index| index as u32 + 1);
+
+    // Only borrow the material mutably when it changes, as that uploads it to the GPU again
+    let changed = shader_mats.get(handle).is_some_and(|mat| {
+        mat.hovered_shape != hovered_shape || mat.selected_shape != selected_shape
+    });
+
+    if let Some(mat) = shader_mats.get_mut(handle).filter(|_| changed) {
+        mat.hovered_shape = hovered_shape;
+        mat.selected_shape = selected_shape;
+    }
+}
@@ -0,0 +1,921 @@
+use bevy::prelude::*;
+
+use std::f32::consts::FRAC_PI_2;
+
+use crate::{
+    camera_controller::{CameraProjection, ShaderCamera},
+    modifiers::{ShapeDisplacement, ShapeDomain},
+    noise::{fractal_bounding, perlin_3d, perlin_fbm_2d, perlin_fbm_3d, simplex_3d, voronoi_3d},
+    shape::{Shape, ShapeFractal, ShapePlane, ShapeTerrain, ShapeType},
+};
+
+const FBM_LACUNARITY: f32 = 2.;
+const FBM_GAIN: f32 = 0.5;
+// The steepest each noise gets, a little above the largest gradients found by searching the noise
+const PERLIN_MAX_GRADIENT: f32 = 3.5;
+const SIMPLEX_MAX_GRADIENT: f32 = 10.;
+// Voronoi distances and the sine product can't change faster than this
+const DEFAULT_MAX_GRADIENT: f32 = 2.;
+// The steepest each octave of the terrain's 2D noise gets, found the same way
+const PERLIN_2D_MAX_GRADIENT: f32 = 3.;
+
+// The same limits as the ray march in ray.wgsl
+const MAX_DIST: f32 = 300.;
+const MAX_STEPS: u32 = 150;
+const EPSILON: f32 = 0.01;
+
+// The Mandelbox's estimate runs a few percent long a couple of units away from it
+const MANDELBOX_STEP_SCALE: f32 = 0.9;
+
+// CPU versions of the signed distance functions in shapes.wgsl, modifiers.wgsl, ray.wgsl and maths.wgsl, these should be kept in sync with
+// the shaders so the scene can be queried outside of the shader (e.g. for picking), and so the shader's distance estimates can be checked
+
+#[derive(Debug, Clone, Copy)]
+pub struct DistanceOutput {
+    pub dist: f32,
+    pub shape_index: Option<usize>,
+    pub step_scale: f32,
+}
+
+pub fn get_distance(
+    p: Vec3,
+    shapes: &[Shape],
+    union_type: u32,
+    smoothness_val: f32,
+    time: f32,
+) -> DistanceOutput {
+    // Far away if every shape is hidden
+    let mut dist: f32 = 9999.;
+    let mut first_shape = true;
+    let mut shape_index = None;
+    // The shapes' distances combined the same way, after each is scaled by its own step scale, so a shape that isn't
+    // the closest still can't be stepped through
+    let mut step_dist: f32 = 9999.;
+
+    let mut closest_or_furthest = if union_type == 0 { 9999. } else { -9999. };
+
+    for (i, shape) in shapes
+        .iter()
+        .enumerate()
+        .filter(|(_, shape)| shape.hidden == 0)
+    {
+        let shape = animate_shape(*shape, time);
+        let shape_p = apply_domain(to_shape_space(p, &shape), shape.pos, &shape.domain);
+        let shape_dist = shape_to_sdf(shape_p, &shape, union_type)
+            + displacement(shape_p, shape.pos, &shape.displacement);
+
+        // If we are finding the minimum of all the shapes, then find closest, otherwise, find furthest
+        if (union_type == 0 && shape_dist < closest_or_furthest)
+            || (union_type != 0 && shape_dist > closest_or_furthest)
+        {
+            closest_or_furthest = shape_dist;
+            shape_index = Some(i);
+        }
+
+        let shape_step_dist = shape_dist
+            * sdf_step_scale(&shape)
+            * domain_step_scale(&shape.domain, bounding_radius(&shape))
+            * displacement_step_scale(&shape.displacement);
+
+        // Min or Max the distances, unless this is the first shape
+        (dist, step_dist) = if first_shape {
+            first_shape = false;
+            (shape_dist, shape_step_dist)
+        } else if union_type == 1 {
+            (dist.max(shape_dist), step_dist.max(shape_step_dist))
+        } else {
+            (
+                smin(dist, shape_dist, smoothness_val),
+                smin(step_dist, shape_step_dist, smoothness_val),
+            )
+        };
+    }
+
+    // Both are combined the same way and the scales are at most 1, so this is at most 1 too
+    let step_scale = if dist > 0. {
+        (step_dist / dist).clamp(0., 1.)
+    } else {
+        1.
+    };
+
+    DistanceOutput {
+        dist,
+        shape_index,
+        step_scale,
+    }
+}
+
+// March a ray through the shapes, returning the distance along it and the shape it hit. Unlike the shader, rays stop
+// at portals rather than passing through them
+pub fn ray_march(
+    ray_origin: Vec3,
+    ray_dir: Vec3,
+    shapes: &[Shape],
+    union_type: u32,
+    smoothness_val: f32,
+    time: f32,
+) -> Option<(f32, usize)> {
+    let mut ray_dist = 0.;
+
+    for _ in 0..MAX_STEPS {
+        let dist_output = get_distance(
+            ray_origin + ray_dir * ray_dist,
+            shapes,
+            union_type,
+            smoothness_val,
+            time,
+        );
+
+        if dist_output.dist <= EPSILON {
+            return dist_output.shape_index.map(|index| (ray_dist, index));
+        }
+
+        ray_dist += dist_output.dist * dist_output.step_scale;
+
+        if ray_dist >= MAX_DIST {
+            break;
+        }
+    }
+
+    None
+}
+
+// The direction away from the nearest surface, from the gradient of the distance like get_normal in lighting.wgsl
+pub fn get_normal(
+    p: Vec3,
+    shapes: &[Shape],
+    union_type: u32,
+    smoothness_val: f32,
+    time: f32,
+) -> Vec3 {
+    let distance = |p: Vec3| get_distance(p, shapes, union_type, smoothness_val, time).dist;
+
+    let dist = distance(p);
+    let normal = dist
+        - Vec3::new(
+            distance(p - Vec3::X * EPSILON),
+            distance(p - Vec3::Y * EPSILON),
+            distance(p - Vec3::Z * EPSILON),
+        );
+
+    normal.normalize_or_zero()
+}
+
+// The direction of the ray through a point on the screen, from centre_and_scale_uv_positions
+pub fn get_ray_dir(camera: &ShaderCamera, uv: Vec2) -> Vec3 {
+    let across = uv.x * camera.right + uv.y * camera.up;
+
+    match CameraProjection::from(camera.projection) {
+        CameraProjection::Perspective => {
+            (camera.forward * camera.focal_length() + across).normalize()
+        }
+        CameraProjection::Orthographic => camera.forward,
+        CameraProjection::Fisheye => {
+            let angle = uv.length() * camera.fov * 0.5;
+            camera.forward * angle.cos() + across.normalize_or_zero() * angle.sin()
+        }
+        CameraProjection::Equirectangular => {
+            let longitude = uv.x * FRAC_PI_2;
+            let latitude = uv.y * FRAC_PI_2;
+            (camera.forward * longitude.cos() + camera.right * longitude.sin()) * latitude.cos()
+                + camera.up * latitude.sin()
+        }
+    }
+}
+
+// Where the ray through a point on the screen starts. Orthographic rays start across the screen, and rays start the near
+// distance in front of the camera, along a plane for perspective and around a sphere for the wide angle projections
+pub fn get_ray_origin(camera: &ShaderCamera, uv: Vec2, ray_dir: Vec3) -> Vec3 {
+    match CameraProjection::from(camera.projection) {
+        CameraProjection::Perspective => {
+            camera.pos + ray_dir * camera.near / ray_dir.dot(camera.forward)
+        }
+        CameraProjection::Orthographic => {
+            camera.pos
+                + (uv.x * camera.right + uv.y * camera.up) * camera.ortho_size
+                + camera.forward * camera.near
+        }
+        _ => camera.pos + ray_dir * camera.near,
+    }
+}
+
+// Convert a position in pixels from the top left of the screen to coordinates which are -1 to 1 along the shorter axis
+pub fn centre_and_scale_uv_positions(uv_pos: Vec2, screen_dim: Vec2) -> Vec2 {
+    let min_screen_size = screen_dim.min_element();
+    let max_screen_size = screen_dim.max_element();
+
+    let uv = uv_pos / screen_dim;
+    let mut coord = Vec2::new(uv.x, 1. - uv.y) * screen_dim / min_screen_size * 2. - 1.;
+
+    let centre_push = (max_screen_size - min_screen_size) / min_screen_size;
+    if screen_dim.x > screen_dim.y {
+        coord.x -= centre_push;
+    } else if screen_dim.x < screen_dim.y {
+        coord.y += centre_push;
+    }
+
+    coord
+}
+
+// Undo the shape's rotation about its position
+pub fn to_shape_space(p: Vec3, shape: &Shape) -> Vec3 {
+    let inverse_rotation = shape.rotation * Vec4::new(-1., -1., -1., 1.);
+
+    rotate_position(p - shape.pos, inverse_rotation) + shape.pos
+}
+
+// Quaternion rotation, written out like maths.wgsl so an all zero quaternion leaves the position alone
+pub fn rotate_position(pos: Vec3, rot: Vec4) -> Vec3 {
+    let axis = rot.truncate();
+
+    pos + 2. * axis.cross(axis.cross(pos) + rot.w * pos)
+}
+
+// Give different motion depending on the shape's ID, so it keeps its motion when the shapes are reordered
+pub fn animate_shape(mut shape: Shape, time: f32) -> Shape {
+    if shape.dynamic != 0
+        || matches!(
+            ShapeType::from(shape.shape_type),
+            ShapeType::Plane | ShapeType::Terrain
+        )
+    {
+        return shape;
+    }
+
+    match shape.id {
+        0 => shape.pos.y += 2. * time.sin(),
+        1 => shape.pos.x += 2. * (time * 2.).cos(),
+        2 => {
+            let i = shape.id as f32;
+            shape.pos.x += i * 3.5 * (time * 1.5 / i + i * 0.5).sin();
+            shape.pos.y += i * 3.5 * (time * 2.5 / i + i * 0.5).cos();
+        }
+        _ => {}
+    }
+
+    shape
+}
+
+pub fn shape_to_sdf(p: Vec3, shape: &Shape, union_type: u32) -> f32 {
+    match ShapeType::from(shape.shape_type) {
+        ShapeType::None => {
+            if union_type == 0 {
+                9999.
+            } else {
+                -9999.
+            }
+        }
+        ShapeType::Sphere => sdf_sphere(p, shape.pos, shape.size.x),
+        ShapeType::Cube => sdf_cube(p, shape.pos, shape.size),
+        ShapeType::Plane => sdf_plane(p, shape.pos, &shape.plane),
+        ShapeType::Portal => sdf_portal(p, shape.pos, shape.size),
+        ShapeType::Mandelbulb => sdf_mandelbulb(p, shape.pos, shape.size.x, &shape.fractal),
+        ShapeType::MengerSponge => sdf_menger_sponge(p, shape.pos, shape.size.x, &shape.fractal),
+        ShapeType::SierpinskiTetrahedron => {
+            sdf_sierpinski_tetrahedron(p, shape.pos, shape.size.x, &shape.fractal)
+        }
+        ShapeType::Mandelbox => sdf_mandelbox(p, shape.pos, shape.size.x, &shape.fractal),
+        ShapeType::Terrain => sdf_terrain(p, shape.pos, &shape.terrain),
+    }
+}
+
+pub fn sdf_step_scale(shape: &Shape) -> f32 {
+    match ShapeType::from(shape.shape_type) {
+        ShapeType::Mandelbox => MANDELBOX_STEP_SCALE,
+        ShapeType::Terrain => terrain_step_scale(&shape.terrain),
+        _ => 1.,
+    }
+}
+
+// How far the shape reaches from its centre, planes and terrain go on forever so are treated as unit sized
+pub fn bounding_radius(shape: &Shape) -> f32 {
+    match ShapeType::from(shape.shape_type) {
+        ShapeType::Sphere => shape.size.x,
+        ShapeType::Cube | ShapeType::Portal => shape.size.length(),
+        ShapeType::Mandelbulb => shape.fractal.bailout * shape.size.x,
+        ShapeType::MengerSponge | ShapeType::SierpinskiTetrahedron => 3f32.sqrt() * shape.size.x,
+        ShapeType::Mandelbox => {
+            let power = shape.fractal.power;
+            let half_width = if power < -1. {
+                2.
+            } else if power > 1. {
+                2. * (power + 1.) / (power - 1.)
+            } else {
+                shape.fractal.bailout
+            };
+
+            3f32.sqrt() * half_width * shape.size.x
+        }
+        ShapeType::None | ShapeType::Plane | ShapeType::Terrain => 1.,
+    }
+}
+
+pub fn sdf_sphere(p: Vec3, centre: Vec3, radius: f32) -> f32 {
+    p.distance(centre) - radius
+}
+
+pub fn sdf_cube(p: Vec3, centre: Vec3, size: Vec3) -> f32 {
+    ((p - centre).abs() - size).max(Vec3::ZERO).length()
+}
+
+pub fn sdf_plane(p: Vec3, centre: Vec3, plane: &ShapePlane) -> f32 {
+    let normal = plane.normal;
+
+    let helper = if normal.x.abs() > 0.9 {
+        Vec3::Z
+    } else {
+        Vec3::X
+    };
+    let tangent = helper.cross(normal).normalize();
+    let bitangent = normal.cross(tangent);
+
+    let q = p - centre;
+    let local = Vec3::new(
+        q.dot(tangent),
+        q.dot(normal) - plane.offset,
+        q.dot(bitangent),
+    );
+
+    let extents = Vec3::new(plane.half_extents.x, plane.thickness, plane.half_extents.y);
+    let finite = BVec3::new(plane.half_extents.x > 0., true, plane.half_extents.y > 0.);
+    let d = Vec3::select(finite, local.abs() - extents, Vec3::splat(-9999.));
+
+    d.max(Vec3::ZERO).length() + d.max_element().min(0.)
+}
+
+pub fn sdf_portal(p: Vec3, centre: Vec3, size: Vec3) -> f32 {
+    ((p - centre).abs() - size).max(Vec3::ZERO).length()
+}
+
+pub fn sdf_mandelbulb(p: Vec3, centre: Vec3, scale: f32, fractal: &ShapeFractal) -> f32 {
+    let mut c = (p - centre) / scale;
+
+    // Outside the bailout radius the estimate grows faster than the distance does, so estimate from where the point's
+    // direction meets the bailout sphere and add on the distance to it
+    let beyond_bailout = (c.length() - fractal.bailout).max(0.);
+    if beyond_bailout > 0. {
+        c *= fractal.bailout / c.length();
+    }
+
+    let mut z = c;
+    let mut dr = 1.;
+    let mut r = z.length();
+
+    for _ in 0..fractal.iterations {
+        r = z.length();
+        if r > fractal.bailout {
+            break;
+        }
+
+        // Raise z to the given power in spherical coordinates
+        let theta = (z.z / r.max(1e-6)).clamp(-1., 1.).acos() * fractal.power;
+        let phi = z.y.atan2(z.x) * fractal.power;
+        dr = r.powf(fractal.power - 1.) * fractal.power * dr + 1.;
+
+        z = r.powf(fractal.power)
+            * Vec3::new(
+                theta.sin() * phi.cos(),
+                phi.sin() * theta.sin(),
+                theta.cos(),
+            )
+            + c;
+    }
+
+    (0.5 * r.max(1e-6).ln() * r / dr + beyond_bailout) * scale
+}
+
+pub fn sdf_menger_sponge(p: Vec3, centre: Vec3, size: f32, fractal: &ShapeFractal) -> f32 {
+    let q = (p - centre) / size;
+    let mut dist = sdf_box(q, Vec3::ONE);
+    let mut scale = 1.;
+
+    for _ in 0..fractal.iterations {
+        let a = (q * scale).rem_euclid(Vec3::splat(2.)) - 1.;
+        scale *= fractal.power;
+
+        let r = (1. - fractal.power * a.abs()).abs();
+        let hole = (r.x.max(r.y).min(r.y.max(r.z).min(r.z.max(r.x))) - 1.) / scale;
+
+        dist = dist.max(hole);
+    }
+
+    dist * size
+}
+
+pub fn sdf_sierpinski_tetrahedron(p: Vec3, centre: Vec3, size: f32, fractal: &ShapeFractal) -> f32 {
+    let mut z = (p - centre) / size;
+
+    let mut iterations = 0;
+    while iterations < fractal.iterations {
+        if z.length_squared() > fractal.bailout * fractal.bailout {
+            break;
+        }
+
+        if z.x + z.y < 0. {
+            z = Vec3::new(-z.y, -z.x, z.z);
+        }
+        if z.x + z.z < 0. {
+            z = Vec3::new(-z.z, z.y, -z.x);
+        }
+        if z.y + z.z < 0. {
+            z = Vec3::new(z.x, -z.z, -z.y);
+        }
+
+        z = z * fractal.power - Vec3::splat(fractal.power - 1.);
+        iterations += 1;
+    }
+
+    sdf_tetrahedron(z) * fractal.power.powi(-(iterations as i32)) * size
+}
+
+pub fn sdf_mandelbox(p: Vec3, centre: Vec3, size: f32, fractal: &ShapeFractal) -> f32 {
+    let min_radius_sq = 0.25;
+    let fixed_radius_sq = 1.;
+
+    let c = (p - centre) / size;
+    let mut z = c;
+    let mut dr = 1.;
+
+    for _ in 0..fractal.iterations {
+        z = z.clamp(Vec3::NEG_ONE, Vec3::ONE) * 2. - z;
+
+        let r_sq = z.length_squared();
+        if r_sq < min_radius_sq {
+            z *= fixed_radius_sq / min_radius_sq;
+            dr *= fixed_radius_sq / min_radius_sq;
+        } else if r_sq < fixed_radius_sq {
+            z *= fixed_radius_sq / r_sq;
+            dr *= fixed_radius_sq / r_sq;
+        }
+
+        z = z * fractal.power + c;
+        dr = dr * fractal.power.abs() + 1.;
+
+        if z.length_squared() > fractal.bailout * fractal.bailout {
+            break;
+        }
+    }
+
+    z.length() / dr.abs() * size
+}
+
+pub fn terrain_height(p: Vec2, centre: Vec3, terrain: &ShapeTerrain) -> f32 {
+    let noise = perlin_fbm_2d(
+        (p - centre.xz()) / terrain.scale,
+        terrain.octaves,
+        terrain.lacunarity,
+        terrain.gain,
+    );
+
+    centre.y + noise * terrain.height
+}
+
+pub fn sdf_terrain(p: Vec3, centre: Vec3, terrain: &ShapeTerrain) -> f32 {
+    p.y - terrain_height(p.xz(), centre, terrain)
+}
+
+pub fn terrain_step_scale(terrain: &ShapeTerrain) -> f32 {
+    let mut octave_gradient = 1.;
+    let mut max_gradient = 0.;
+    for _ in 0..terrain.octaves {
+        max_gradient += octave_gradient;
+        octave_gradient *= terrain.gain * terrain.lacunarity;
+    }
+
+    let slope = PERLIN_2D_MAX_GRADIENT
+        * max_gradient
+        * fractal_bounding(terrain.octaves, terrain.gain)
+        * terrain.height
+        / terrain.scale;
+
+    1. / (1. + slope * slope).sqrt()
+}
+
+pub fn sdf_box(p: Vec3, size: Vec3) -> f32 {
+    let q = p.abs() - size;
+
+    q.max(Vec3::ZERO).length() + q.max_element().min(0.)
+}
+
+pub fn sdf_tetrahedron(p: Vec3) -> f32 {
+    ((-p.x - p.y - p.z)
+        .max(p.x + p.y - p.z)
+        .max((-p.x + p.y + p.z).max(p.x - p.y + p.z))
+        - 1.)
+        / 3f32.sqrt()
+}
+
+pub fn apply_domain(p: Vec3, centre: Vec3, domain: &ShapeDomain) -> Vec3 {
+    let mut q = mirror(p, domain.mirror) - centre;
+
+    q = match domain.repetition {
+        1 => repeat(q, domain.period),
+        2 => repeat_limited(q, domain.period, domain.limit),
+        3 => repeat_polar(q, domain.polar_count, domain.polar_radius),
+        _ => q,
+    };
+
+    q = match domain.deform {
+        1 => twist(q, domain.deform_amount),
+        2 => bend(q, domain.deform_amount),
+        _ => q,
+    };
+
+    q + centre
+}
+
+pub fn domain_step_scale(domain: &ShapeDomain, radius: f32) -> f32 {
+    if domain.deform == 0 {
+        1.
+    } else {
+        1. / (1. + domain.deform_amount.abs() * radius)
+    }
+}
+
+pub fn displacement(p: Vec3, centre: Vec3, displacement: &ShapeDisplacement) -> f32 {
+    let q = (p - centre) * displacement.frequency;
+
+    let noise = match displacement.displacement_type {
+        1 => perlin_3d(q),
+        2 => simplex_3d(q),
+        3 => perlin_fbm_3d(q, displacement.octaves, FBM_LACUNARITY, FBM_GAIN),
+        4 => voronoi_3d(q),
+        5 => q.x.sin() * q.y.sin() * q.z.sin(),
+        _ => return 0.,
+    };
+
+    noise * displacement.amplitude
+}
+
+pub fn displacement_step_scale(displacement: &ShapeDisplacement) -> f32 {
+    if displacement.displacement_type == 0 {
+        return 1.;
+    }
+
+    let max_gradient = match displacement.displacement_type {
+        1 => PERLIN_MAX_GRADIENT,
+        2 => SIMPLEX_MAX_GRADIENT,
+        // Each octave of fBm adds the same gradient, as the frequency doubles while the amplitude halves
+        3 => {
+            PERLIN_MAX_GRADIENT
+                * displacement.octaves as f32
+                * fractal_bounding(displacement.octaves, FBM_GAIN)
+        }
+        _ => DEFAULT_MAX_GRADIENT,
+    };
+
+    1. / (1. + displacement.amplitude.abs() * displacement.frequency * max_gradient)
+}
+
+pub fn mirror(p: Vec3, axes: u32) -> Vec3 {
+    Vec3::select(
+        BVec3::new(axes & 1 != 0, axes & 2 != 0, axes & 4 != 0),
+        p.abs(),
+        p,
+    )
+}
+
+pub fn repeat(p: Vec3, period: Vec3) -> Vec3 {
+    let repeated = period.cmpgt(Vec3::ZERO);
+    let safe_period = Vec3::select(repeated, period, Vec3::ONE);
+
+    Vec3::select(repeated, p - safe_period * (p / safe_period).round(), p)
+}
+
+pub fn repeat_limited(p: Vec3, period: Vec3, limit: Vec3) -> Vec3 {
+    let repeated = period.cmpgt(Vec3::ZERO);
+    let safe_period = Vec3::select(repeated, period, Vec3::ONE);
+
+    Vec3::select(
+        repeated,
+        p - safe_period * (p / safe_period).round().clamp(-limit, limit),
+        p,
+    )
+}
+
+pub fn repeat_polar(p: Vec3, count: u32, radius: f32) -> Vec3 {
+    if count == 0 {
+        return p;
+    }
+
+    let sector = std::f32::consts::TAU / count as f32;
+    let angle = p.z.atan2(p.x);
+    let folded_angle = angle - sector * (angle / sector).round();
+    let r = p.xz().length();
+
+    Vec3::new(r * folded_angle.cos() - radius, p.y, r * folded_angle.sin())
+}
+
+pub fn twist(p: Vec3, amount: f32) -> Vec3 {
+    let (s, c) = (amount * p.y).sin_cos();
+
+    Vec3::new(c * p.x - s * p.z, p.y, s * p.x + c * p.z)
+}
+
+pub fn bend(p: Vec3, amount: f32) -> Vec3 {
+    let (s, c) = (amount * p.x).sin_cos();
+
+    Vec3::new(c * p.x - s * p.y, s * p.x + c * p.y, p.z)
+}
+
+pub fn smin(a: f32, b: f32, c: f32) -> f32 {
+    a.min(b) - c / 6. * ((c - (a - b).abs()).max(0.) / c).powi(3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Shapes from 3 up aren't animated, so they stay where they are put
+    const STILL_ID: u32 = 3;
+
+    fn shape(shape_type: ShapeType) -> Shape {
+        Shape {
+            shape_type: shape_type.into(),
+            id: STILL_ID,
+            fractal: ShapeFractal::for_shape_type(shape_type),
+            ..default()
+        }
+    }
+
+    fn distance(p: Vec3, shape: Shape) -> DistanceOutput {
+        get_distance(p, &[shape], 0, 0.01, 0.)
+    }
+
+    // Points spread through a cube, from a xorshift generator so every run checks the same ones
+    fn random_points(count: usize, extent: f32, mut seed: u32) -> Vec<Vec3> {
+        let mut random = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed as f32 / u32::MAX as f32 * 2. - 1.
+        };
+
+        (0..count)
+            .map(|_| Vec3::new(random(), random(), random()) * extent)
+            .collect()
+    }
+
+    // The points of a grid over the cube that the ray march would count as hits
+    fn solid_samples(sdf: impl Fn(Vec3) -> f32, extent: f32, spacing: f32) -> Vec<Vec3> {
+        let count = (2. * extent / spacing) as i32;
+        let mut samples = Vec::new();
+        for x in 0..=count {
+            for y in 0..=count {
+                for z in 0..=count {
+                    let p = IVec3::new(x, y, z).as_vec3() * spacing - extent;
+                    if sdf(p) <= EPSILON {
+                        samples.push(p);
+                    }
+                }
+            }
+        }
+
+        samples
+    }
+
+    // The step the ray takes from every point must not carry it past the nearest solid sample. As the samples are
+    // within EPSILON of the surface, allow for that much
+    fn assert_conservative(name: &str, shape: Shape, extent: f32, spacing: f32) {
+        let solid = solid_samples(|p| distance(p, shape).dist, extent, spacing);
+        assert!(!solid.is_empty(), "{name} has no surface");
+
+        for p in random_points(300, extent * 2., 1234) {
+            let brute_force = solid.iter().map(|q| q.distance(p)).fold(f32::MAX, f32::min);
+            let output = distance(p, shape);
+            let step = output.dist * output.step_scale;
+
+            assert!(
+                step <= brute_force + EPSILON,
+                "{name} steps {step} from {p}, but the surface is {brute_force} away"
+            );
+        }
+    }
+
+    // Where the steps are scaled, the scaled distance can't change faster than the point moves. Only checked outside the
+    // shape, as that's where the ray steps
+    fn assert_lipschitz(name: &str, shape: Shape, extent: f32) {
+        let offset = 0.01;
+        let directions = random_points(5000, 1., 99);
+
+        for (a, direction) in random_points(5000, extent, 42).into_iter().zip(directions) {
+            let b = a + direction.normalize() * offset;
+            let (output_a, output_b) = (distance(a, shape), distance(b, shape));
+            if output_a.dist.min(output_b.dist) <= EPSILON {
+                continue;
+            }
+
+            let change = (output_a.dist - output_b.dist).abs() * output_a.step_scale;
+            assert!(
+                change <= offset * 1.001,
+                "{name} changes by {change} between {a} and {b}, which are {offset} apart"
+            );
+        }
+    }
+
+    #[test]
+    fn fractals_match_known_distances() {
+        let sponge = ShapeFractal::for_shape_type(ShapeType::MengerSponge);
+        let tetrahedron = ShapeFractal::for_shape_type(ShapeType::SierpinskiTetrahedron);
+
+        // With no iterations they are the shapes they are carved from
+        for p in random_points(100, 3., 7) {
+            let uncarved = ShapeFractal {
+                iterations: 0,
+                ..sponge
+            };
+            assert!(
+                (sdf_menger_sponge(p, Vec3::ZERO, 1., &uncarved) - sdf_box(p, Vec3::ONE)).abs()
+                    < 1e-5
+            );
+
+            let unfolded = ShapeFractal {
+                iterations: 0,
+                ..tetrahedron
+            };
+            assert!(
+                (sdf_sierpinski_tetrahedron(p, Vec3::ZERO, 1., &unfolded) - sdf_tetrahedron(p))
+                    .abs()
+                    < 1e-5
+            );
+        }
+
+        // The sponge's corners are never carved away, so past one the distance is exact
+        let corner = sdf_menger_sponge(Vec3::splat(2.), Vec3::ZERO, 1., &sponge);
+        assert!((corner - 3f32.sqrt()).abs() < 1e-5);
+        // Its centre is in the middle of the largest hole
+        assert!((sdf_menger_sponge(Vec3::ZERO, Vec3::ZERO, 1., &sponge) - 1. / 3.).abs() < 1e-5);
+
+        // The tetrahedron's vertices are fixed by the folds, so are part of every iteration
+        let vertex = Vec3::ONE;
+        assert!(sdf_sierpinski_tetrahedron(vertex, Vec3::ZERO, 1., &tetrahedron).abs() < EPSILON);
+
+        // The origin's orbit never leaves it, and far away the bulb is about as far as its bailout sphere
+        let bulb = ShapeFractal::for_shape_type(ShapeType::Mandelbulb);
+        assert!(sdf_mandelbulb(Vec3::ZERO, Vec3::ZERO, 1., &bulb) <= EPSILON);
+        let far = sdf_mandelbulb(Vec3::X * 100., Vec3::ZERO, 1., &bulb);
+        assert!(far < 100. - 1. && far > 100. - bulb.bailout - 1.);
+
+        // Moving and scaling the fractal moves and scales its distance
+        let centre = Vec3::new(2., -1., 3.);
+        let p = Vec3::new(0.3, 2., -0.5);
+        let scaled = sdf_mandelbulb(centre + p * 2., centre, 2., &bulb);
+        assert!((scaled - sdf_mandelbulb(p, Vec3::ZERO, 1., &bulb) * 2.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn fractals_are_conservative() {
+        assert_conservative("Mandelbulb", shape(ShapeType::Mandelbulb), 1.6, 0.04);
+        assert_conservative("Menger sponge", shape(ShapeType::MengerSponge), 1.6, 0.04);
+        assert_conservative(
+            "Sierpinski tetrahedron",
+            shape(ShapeType::SierpinskiTetrahedron),
+            1.6,
+            0.02,
+        );
+        // The Mandelbox spreads out to about 4 with a power of -1.5
+        assert_conservative("Mandelbox", shape(ShapeType::Mandelbox), 4.5, 0.06);
+    }
+
+    #[test]
+    fn repetition_matches_known_distances() {
+        let mut sphere = shape(ShapeType::Sphere);
+        sphere.size.x = 0.5;
+
+        // Copies every 2 along x
+        let mut repeated = sphere;
+        repeated.domain.repetition = 1;
+        repeated.domain.period = Vec3::new(2., 0., 0.);
+        assert!((distance(Vec3::new(4.9, 0., 0.), repeated).dist - 0.4).abs() < 1e-5);
+        assert!(
+            (distance(Vec3::new(-3., 1., 0.), repeated).dist - (2f32.sqrt() - 0.5)).abs() < 1e-5
+        );
+        // Axes with no period aren't repeated
+        assert!((distance(Vec3::new(0., 4., 0.), repeated).dist - 3.5).abs() < 1e-5);
+
+        // One copy either side, so the nearest to 6 is the one at 2
+        let mut limited = repeated;
+        limited.domain.repetition = 2;
+        limited.domain.limit = Vec3::ONE;
+        assert!((distance(Vec3::new(6., 0., 0.), limited).dist - 3.5).abs() < 1e-5);
+        assert!((distance(Vec3::new(-2.2, 0., 0.), limited).dist + 0.3).abs() < 1e-5);
+
+        // Four copies around the Y axis, 2 out from it
+        let mut polar = sphere;
+        polar.domain.repetition = 3;
+        polar.domain.polar_count = 4;
+        polar.domain.polar_radius = 2.;
+        for copy in [Vec3::X, Vec3::Z, Vec3::NEG_X, Vec3::NEG_Z] {
+            let p = copy * 2.7 + Vec3::Y * 0.1;
+            let expected = (0.7f32 * 0.7 + 0.1 * 0.1).sqrt() - 0.5;
+            assert!((distance(p, polar).dist - expected).abs() < 1e-4);
+        }
+
+        // Mirroring across x puts a copy of the shape on the other side
+        let mut mirrored = sphere;
+        mirrored.pos = Vec3::new(2., 0., 0.);
+        mirrored.domain.mirror = 1;
+        assert!((distance(Vec3::new(-2.6, 0., 0.), mirrored).dist - 0.1).abs() < 1e-5);
+        assert!((distance(Vec3::new(0., 0., 0.), mirrored).dist - 1.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn deformed_shapes_are_conservative() {
+        // Space is turned further from the axis, so larger shapes are checked over a larger area too
+        for size in [1., 3.] {
+            for (deform, amount) in [(1, 0.5), (1, 2.), (2, 0.5), (2, 2.)] {
+                let mut cube = shape(ShapeType::Cube);
+                cube.size = Vec3::splat(size);
+                cube.domain.deform = deform;
+                cube.domain.deform_amount = amount;
+
+                let name = format!("Size {size} cube deformed {deform} by {amount}");
+                assert_conservative(&name, cube, 2. * size, 0.05 * size);
+                assert_lipschitz(&name, cube, 3. * size);
+            }
+        }
+    }
+
+    #[test]
+    fn displaced_shapes_are_conservative() {
+        for displacement_type in 1..=5 {
+            let mut sphere = shape(ShapeType::Sphere);
+            sphere.displacement.displacement_type = displacement_type;
+            sphere.displacement.amplitude = 0.3;
+
+            let name = format!("Displacement {displacement_type}");
+            assert_conservative(&name, sphere, 1.5, 0.04);
+            assert_lipschitz(&name, sphere, 2.);
+        }
+    }
+
+    #[test]
+    fn terrain_height_matches_known_values() {
+        let terrain = ShapeTerrain::default();
+        let centre = Vec3::new(1., -2., 0.5);
+
+        // The noise is zero at the centre, so the ground is at the centre's height
+        assert_eq!(terrain_height(centre.xz(), centre, &terrain), centre.y);
+
+        let known = [
+            (Vec2::new(0.9, 2.1), -2.135_593_4),
+            (Vec2::new(-3.75, 7.5), -1.383_932_8),
+            (Vec2::new(30.3, -10.8), -1.850_505_5),
+            (Vec2::new(1.5, 1.5), -1.821_295),
+        ];
+        for (p, expected) in known {
+            let height = terrain_height(p, centre, &terrain);
+            assert!(
+                (height - expected).abs() < 1e-5,
+                "The ground at {p} is at {height}, not {expected}"
+            );
+            assert_eq!(
+                sdf_terrain(p.extend(4.).xzy(), centre, &terrain),
+                4. - height
+            );
+        }
+    }
+
+    // On steep terrain the vertical distance is much further than the nearest slope, the step scale has to make up for
+    // that. The nearest point on a fine grid of the heightfield is never nearer than the true nearest point
+    #[test]
+    fn terrain_step_scale_is_conservative_on_steep_terrain() {
+        let (extent, spacing) = (6., 0.02);
+
+        // A single octave is where the steepest slope is closest to the bound
+        for octaves in [1, 5] {
+            let terrain = ShapeTerrain {
+                octaves,
+                scale: 2.,
+                height: 3.,
+                ..default()
+            };
+
+            let count = (2. * extent / spacing) as i32;
+            let ground: Vec<Vec3> = (0..=count)
+                .flat_map(|x| {
+                    (0..=count).map(move |z| IVec2::new(x, z).as_vec2() * spacing - extent)
+                })
+                .map(|p| p.extend(terrain_height(p, Vec3::ZERO, &terrain)).xzy())
+                .collect();
+
+            let step_scale = terrain_step_scale(&terrain);
+            let heights = random_points(200, 1., 6);
+            for (p, height) in random_points(200, extent - 2., 5).into_iter().zip(heights) {
+                let above = terrain_height(p.xz(), Vec3::ZERO, &terrain) + 0.05 + height.y.abs();
+                let p = Vec3::new(p.x, above, p.z);
+
+                let brute_force = ground
+                    .iter()
+                    .map(|q| q.distance(p))
+                    .fold(f32::MAX, f32::min);
+                let step = sdf_terrain(p, Vec3::ZERO, &terrain) * step_scale;
+
+                assert!(
+                    step <= brute_force + 1e-4,
+                    "Steps {step} from {p} over {octaves} octaves, but the ground is {brute_force} away"
+                );
+            }
+        }
+    }
+}
@@ -5,6 +5,15 @@ pub const SHAPE_SHADER: Handle<Shader> = Handle::weak_from_u128(1234567890123456
 pub const RAY_SHADER: Handle<Shader> = Handle::weak_from_u128(12345678901234567892);
 pub const LIGHTING_SHADER: Handle<Shader> = Handle::weak_from_u128(12345678901234567893);
 pub const MATHS_SHADER: Handle<Shader> = Handle::weak_from_u128(12345678901234567894);
+pub const MODIFIERS_SHADER: Handle<Shader> = Handle::weak_from_u128(12345678901234567895);
+pub const NOISE_SHADER: Handle<Shader> = Handle::weak_from_u128(12345678901234567896);
+pub const MATERIALS_SHADER: Handle<Shader> = Handle::weak_from_u128(12345678901234567897);
+pub const TONEMAPPING_SHADER: Handle<Shader> = Handle::weak_from_u128(12345678901234567898);
+pub const SKY_SHADER: Handle<Shader> = Handle::weak_from_u128(12345678901234567899);
+pub const FOG_SHADER: Handle<Shader> = Handle::weak_from_u128(12345678901234567900);
+pub const OUTLINE_SHADER: Handle<Shader> = Handle::weak_from_u128(12345678901234567901);
+pub const DEBUG_SHADER: Handle<Shader> = Handle::weak_from_u128(12345678901234567902);
+pub const STEREO_SHADER: Handle<Shader> = Handle::weak_from_u128(12345678901234567903);
 
 pub struct ShaderLoaderPlugin;
 
@@ -41,5 +50,59 @@ impl Plugin for ShaderLoaderPlugin {
             "../assets/shaders/maths.wgsl",
             Shader::from_wgsl
         );
+        load_internal_asset!(
+            app,
+            MODIFIERS_SHADER,
+            "../assets/shaders/modifiers.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            NOISE_SHADER,
+            "../assets/shaders/noise.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            MATERIALS_SHADER,
+            "../assets/shaders/materials.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            TONEMAPPING_SHADER,
+            "../assets/shaders/tonemapping.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            SKY_SHADER,
+            "../assets/shaders/sky.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            FOG_SHADER,
+            "../assets/shaders/fog.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            OUTLINE_SHADER,
+            "../assets/shaders/outline.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            DEBUG_SHADER,
+            "../assets/shaders/debug.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            STEREO_SHADER,
+            "../assets/shaders/stereo.wgsl",
+            Shader::from_wgsl
+        );
     }
 }
@@ -2,20 +2,37 @@ use bevy::{
     prelude::*,
     render::render_resource::{AsBindGroup, ShaderRef},
     sprite::Material2d,
+    utils::HashSet,
 };
 use bevy_inspector_egui::{
-    prelude::ReflectInspectorOptions, quick::ResourceInspectorPlugin, InspectorOptions,
+    inspector_egui_impls::InspectorEguiImpl, prelude::ReflectInspectorOptions,
+    quick::ResourceInspectorPlugin, InspectorOptions,
 };
 
 use crate::{
     camera_controller::{
         get_camera_axes, ShaderCamera, ShaderCameraControllerPlugin, ShaderCameraInspector,
-        CAMERA_DEFAULT_ZOOM,
+        CAMERA_DEFAULT_FOV, CAMERA_DEFAULT_ORTHO_SIZE,
     },
-    fullscreen_shader::FullscreenShaderPlugin,
-    light::{ShaderLight, ShaderLightInspector},
+    capture::CapturePlugin,
+    clock::{ShaderClock, ShaderClockPlugin, ShaderClockSet},
+    debug::{DebugViewPlugin, ShaderDebug, ShaderDebugInspector},
+    fog::{ShaderFog, ShaderFogInspector, ShaderVolume},
+    fullscreen_shader::{FullscreenCover, FullscreenShaderPlugin},
+    gizmo::TransformGizmoPlugin,
+    history::SceneHistoryPlugin,
+    light::{ShaderLight, ShaderLightInspector, ShadingModel},
+    materials::{load_texture, MaterialPattern, ShapeMaterial, TEXTURE_SLOTS},
+    noise::{permutation_table, NOISE_SEED},
+    outline::{ShaderOutline, ShaderOutlineInspector},
+    outliner::{OutlinerPlugin, ShapeGroup},
+    physics::ShapePhysicsPlugin,
+    picking::ShapePickingPlugin,
     shader_loader::ShaderLoaderPlugin,
-    shape::{Shape, ShapeInspector, ShapeType},
+    shape::{PlanePattern, Shape, ShapeFractal, ShapeId, ShapeInspector, ShapePlane, ShapeType},
+    sky::{ShaderSky, ShaderSkyInspector},
+    stereo::{ShaderStereo, ShaderStereoInspector},
+    tonemapping::{Tonemapper, EXPOSURE_RANGE},
     UnionType,
 };
 
@@ -33,64 +50,116 @@ impl Plugin for ShaderMatPlugin {
                 shape_type: ShapeType::Sphere.into(),
                 pos: Vec3::new(0., 2.5, 0.),
                 size: Vec3::new(2.5, 0., 0.),
+                ..default()
             },
             Shape {
                 shape_type: ShapeType::Cube.into(),
                 pos: Vec3::new(0., -0.5, 0.),
+                material: ShapeMaterial {
+                    pattern: MaterialPattern::Checker.into(),
+                    ..default()
+                },
                 ..default()
             },
             Shape {
                 shape_type: ShapeType::Sphere.into(),
                 pos: Vec3::new(4., 1., 0.),
                 size: Vec3::new(1.5, 0.0, 0.),
+                ..default()
             },
             Shape {
                 shape_type: ShapeType::Cube.into(),
                 pos: Vec3::new(4., 1., 0.),
                 size: Vec3::splat(1.25),
+                ..default()
             },
             Shape {
                 shape_type: ShapeType::Sphere.into(),
                 pos: Vec3::new(4., 1., 0.),
                 size: Vec3::new(1., 0.0, 0.),
+                ..default()
             },
             Shape {
                 shape_type: ShapeType::Cube.into(),
                 pos: Vec3::new(4., 1., 0.),
                 size: Vec3::splat(0.75),
+                ..default()
+            },
+            Shape {
+                shape_type: ShapeType::Plane.into(),
+                pos: Vec3::new(4., -1.5, 0.),
+                plane: ShapePlane {
+                    thickness: 0.1,
+                    half_extents: Vec2::splat(3.),
+                    pattern: PlanePattern::Checkerboard.into(),
+                    ..default()
+                },
+                ..default()
+            },
+            Shape {
+                shape_type: ShapeType::Terrain.into(),
+                pos: Vec3::new(0., -4., 0.),
+                ..default()
             },
-            // Shape {
-            //     shape_type: ShapeType::Plane.into(),
-            //     pos: Vec3::new(0., 1., 0.),
-            //     size: Vec3::new(-10., 1., 0.),
-            // },
             Shape {
                 shape_type: ShapeType::Portal.into(),
                 pos: Vec3::new(3., 2., -5.),
                 size: Vec3::new(1.2, 2.0, 0.1),
+                ..default()
+            },
+            Shape {
+                shape_type: ShapeType::Mandelbulb.into(),
+                pos: Vec3::new(-5., 1.5, 0.),
+                size: Vec3::new(1.5, 0., 0.),
+                fractal: ShapeFractal::for_shape_type(ShapeType::Mandelbulb),
+                ..default()
             },
         ];
 
+        // The first shapes' IDs match their indices, which the animation in animate_shape is written for
+        let shapes = shapes
+            .into_iter()
+            .enumerate()
+            .map(|(i, shape)| Shape {
+                id: i as u32,
+                ..shape
+            })
+            .collect::<Vec<_>>();
         let shapes_len = shapes.len() as u32;
 
         let shader_mat = ShaderMat {
             shapes,
             shapes_len,
+            noise_permutation: permutation_table(NOISE_SEED),
             union_type: 0,
             // smoothness_val: 5.,
             smoothness_val: 0.01,
             light: ShaderLight {
                 pos: Vec3::new(0., 5., 0.),
                 colour: Vec3::new(0.8, 0.5, 0.5),
+                ..default()
             },
             camera: ShaderCamera {
                 pos: camera_pos,
-                zoom: CAMERA_DEFAULT_ZOOM,
+                fov: CAMERA_DEFAULT_FOV.to_radians(),
+                ortho_size: CAMERA_DEFAULT_ORTHO_SIZE,
                 rotation: camera_rotation.into(),
                 forward,
                 right,
                 up,
+                ..default()
             },
+            // A cloud drifting over the scene
+            fog: ShaderFog {
+                volumes_len: 1,
+                volumes: vec_to_sized_array(vec![ShaderVolume {
+                    pos: Vec3::new(-3., 7., 3.),
+                    size: Vec3::new(3., 0., 0.),
+                    ..default()
+                }]),
+                ..default()
+            },
+            tonemapper: Tonemapper::default().into(),
             ..default()
         };
 
@@ -99,40 +168,82 @@ impl Plugin for ShaderMatPlugin {
         })
         .add_plugins(ShaderCameraControllerPlugin)
         .add_plugins(ShaderLoaderPlugin)
+        .add_plugins(ShaderClockPlugin)
+        .add_plugins(CapturePlugin)
+        .add_plugins(DebugViewPlugin)
+        .add_plugins(ShapePickingPlugin)
+        .add_plugins(TransformGizmoPlugin)
+        .add_plugins(SceneHistoryPlugin)
+        .add_plugins(OutlinerPlugin)
+        .add_plugins(ShapePhysicsPlugin)
         .insert_resource(ShaderMatInspector::from(shader_mat))
         .register_type::<ShaderMatInspector>()
+        // IDs are shown as a label, so can't be edited
+        .register_type::<ShapeId>()
+        .register_type_data::<ShapeId, InspectorEguiImpl>()
         .add_plugins(ResourceInspectorPlugin::<ShaderMatInspector>::default())
         .add_systems(
             Update,
             (
-                update_shadermat_from_egui.run_if(resource_changed::<ShaderMatInspector>),
-                update_time,
+                (assign_unique_shape_ids, update_shadermat_from_egui)
+                    .chain()
+                    .run_if(resource_changed::<ShaderMatInspector>),
+                update_time.after(ShaderClockSet),
             ),
         );
     }
 }
 
-fn update_shadermat_from_egui(
+fn assign_unique_shape_ids(mut inspector_mat: ResMut<ShaderMatInspector>) {
+    // Only borrow the inspector mutably when something needs fixing, so this isn't seen as an edit every frame
+    if inspector_mat.has_duplicate_shape_ids() {
+        inspector_mat.assign_unique_shape_ids();
+    }
+}
+
+pub fn update_shadermat_from_egui(
+    cover: Query<&Handle<ShaderMat>, With<FullscreenCover>>,
     mut shader_mats: ResMut<Assets<ShaderMat>>,
     inspector_mat: Res<ShaderMatInspector>,
+    asset_server: Res<AssetServer>,
 ) {
-    for (_handle, mat) in shader_mats.iter_mut() {
-        mat.shapes = inspector_mat
-            .shapes
-            .clone()
-            .into_iter()
-            .map(|shape| shape.into())
-            .collect::<Vec<_>>();
-        mat.union_type = inspector_mat.union_type.into();
-        mat.smoothness_val = inspector_mat.smoothness_val;
-        mat.light = inspector_mat.light.into();
-        mat.camera.modify(inspector_mat.camera);
+    // Only the material on screen, captures render from their own copy which they set up themselves
+    let Some(mat) = cover
+        .get_single()
+        .ok()
+        .and_then(|handle| shader_mats.get_mut(handle))
+    else {
+        return;
+    };
+
+    mat.shapes = inspector_mat.shader_shapes();
+    mat.shapes_len = mat.shapes.len() as u32;
+    // Storage buffers can't be empty, so keep a hidden shape in there which the shader won't reach
+    if mat.shapes.is_empty() {
+        mat.shapes.push(Shape {
+            hidden: 1,
+            ..default()
+        });
     }
+    mat.union_type = inspector_mat.union_type.into();
+    mat.smoothness_val = inspector_mat.smoothness_val;
+    mat.light = inspector_mat.light.into();
+    mat.sky = inspector_mat.sky.into();
+    mat.fog = inspector_mat.fog.clone().into();
+    mat.shading_model = inspector_mat.shading_model.into();
+    mat.exposure = inspector_mat.exposure;
+    mat.tonemapper = inspector_mat.tonemapper.into();
+    mat.outline = inspector_mat.outline.into();
+    mat.stereo = inspector_mat.stereo.into();
+    mat.debug = inspector_mat.debug.into();
+    mat.camera.modify(inspector_mat.camera);
+    mat.texture_0 = load_texture(&asset_server, &inspector_mat.texture_paths[0]);
+    mat.texture_1 = load_texture(&asset_server, &inspector_mat.texture_paths[1]);
 }
 
-fn update_time(time: Res<Time>, mut shader_mats: ResMut<Assets<ShaderMat>>) {
+pub fn update_time(clock: Res<ShaderClock>, mut shader_mats: ResMut<Assets<ShaderMat>>) {
     for (_handle, mat) in shader_mats.iter_mut() {
-        mat.time = time.elapsed_seconds();
+        mat.time = clock.elapsed;
     }
 }
 
@@ -163,18 +274,71 @@ pub struct ShaderMat {
     pub camera: ShaderCamera,
     #[uniform(0)]
     pub time: f32,
+    // Overrides the screen used to calculate ray directions, so a capture can render part of a larger image
+    #[uniform(0)]
+    pub viewport_offset: Vec2,
+    #[uniform(0)]
+    pub viewport_size: Vec2,
+    #[uniform(0)]
+    pub shading_model: u32,
+    // In stops, applied before tone mapping
+    #[uniform(0)]
+    pub exposure: f32,
+    #[uniform(0)]
+    pub tonemapper: u32,
+    // The shapes under the cursor and selected by clicking, 0 for none, otherwise the shape's index plus one
+    #[uniform(0)]
+    pub hovered_shape: u32,
+    #[uniform(0)]
+    pub selected_shape: u32,
+    #[uniform(0)]
+    pub outline: ShaderOutline,
+    #[uniform(0)]
+    pub stereo: ShaderStereo,
+    #[uniform(0)]
+    pub debug: ShaderDebug,
     #[uniform(2)]
     pub shapes_len: u32,
+    // Lookup table for the noise in noise.wgsl
+    #[storage(3, read_only)]
+    pub noise_permutation: Vec<u32>,
+    // Image textures that shape materials can use, sampled from materials.wgsl
+    #[texture(4)]
+    #[sampler(5)]
+    pub texture_0: Option<Handle<Image>>,
+    #[texture(6)]
+    #[sampler(7)]
+    pub texture_1: Option<Handle<Image>>,
+    // Read by sky.wgsl, which is imported by both the ray marching and the lighting
+    #[uniform(8)]
+    pub sky: ShaderSky,
+    // Read by fog.wgsl, a separate binding as it's large and only used after the ray march
+    #[uniform(9)]
+    pub fog: ShaderFog,
 }
 
 #[derive(Debug, Clone, Asset, Reflect, Resource, InspectorOptions, Component, Default)]
 #[reflect(Resource, InspectorOptions)]
 pub struct ShaderMatInspector {
     pub shapes: Vec<ShapeInspector>,
+    // Named groups of shapes, shown in the outliner
+    pub groups: Vec<ShapeGroup>,
     pub union_type: UnionType,
     pub smoothness_val: f32,
     pub light: ShaderLightInspector,
+    pub sky: ShaderSkyInspector,
+    pub fog: ShaderFogInspector,
+    pub shading_model: ShadingModel,
+    #[inspector(min = -EXPOSURE_RANGE, max = EXPOSURE_RANGE)]
+    pub exposure: f32,
+    pub tonemapper: Tonemapper,
+    pub outline: ShaderOutlineInspector,
+    pub stereo: ShaderStereoInspector,
+    // Also switched with the number keys
+    pub debug: ShaderDebugInspector,
     pub camera: ShaderCameraInspector,
+    // Paths in the assets folder of the images used by the shapes' material textures
+    pub texture_paths: [String; TEXTURE_SLOTS],
 }
 
 impl From<ShaderMat> for ShaderMatInspector {
@@ -185,24 +349,156 @@ impl From<ShaderMat> for ShaderMatInspector {
                 .into_iter()
                 .map(|shape| shape.into())
                 .collect::<Vec<_>>(),
+            groups: Vec::new(),
             union_type: shader_mat.union_type.into(),
             smoothness_val: shader_mat.smoothness_val,
             light: shader_mat.light.into(),
+            sky: shader_mat.sky.into(),
+            fog: shader_mat.fog.into(),
+            shading_model: shader_mat.shading_model.into(),
+            exposure: shader_mat.exposure,
+            tonemapper: shader_mat.tonemapper.into(),
+            outline: shader_mat.outline.into(),
+            stereo: shader_mat.stereo.into(),
+            debug: shader_mat.debug.into(),
             camera: shader_mat.camera.into(),
+            texture_paths: default(),
         }
     }
 }
 
-// // Convert a vector to a sized array, with empty values when the vec is not big enough for the array
-// fn vec_to_sized_array<T: Default + Copy, const N: usize>(vec: Vec<T>) -> [T; N] {
-//     vec.try_into().unwrap_or_else(|vec: Vec<T>| {
-//         vec.into_iter()
-//             .enumerate()
-//             .take(N)
-//             .fold([T::default(); N], |mut acc, (i, elem)| {
-//                 acc[i] = elem;
-
-//                 acc
-//             })
-//     })
-// }
+impl ShaderMatInspector {
+    fn shape_group(&self, index: usize) -> Option<&ShapeGroup> {
+        self.shapes
+            .get(index)
+            .and_then(|shape| shape.group)
+            .and_then(|group| self.groups.get(group))
+    }
+
+    // Whether a shape is hidden, either itself or by its group
+    pub fn is_shape_hidden(&self, index: usize) -> bool {
+        self.shapes.get(index).is_some_and(|shape| shape.hidden)
+            || self.shape_group(index).is_some_and(|group| group.hidden)
+    }
+
+    // Whether a shape is locked, either itself or by its group
+    pub fn is_shape_locked(&self, index: usize) -> bool {
+        self.shapes.get(index).is_some_and(|shape| shape.locked)
+            || self.shape_group(index).is_some_and(|group| group.locked)
+    }
+
+    // An ID which no shape has yet
+    pub fn next_shape_id(&self) -> ShapeId {
+        ShapeId(
+            self.shapes
+                .iter()
+                .map(|shape| shape.id.0 + 1)
+                .max()
+                .unwrap_or(0),
+        )
+    }
+
+    // Where the shape with an ID or name currently is in the list, so other code can refer to shapes by ID or name
+    // and not be affected by them being reordered
+    pub fn shape_index_by_id(&self, id: ShapeId) -> Option<usize> {
+        self.shapes.iter().position(|shape| shape.id == id)
+    }
+
+    pub fn shape_index_by_name(&self, name: &str) -> Option<usize> {
+        self.shapes.iter().position(|shape| shape.name == name)
+    }
+
+    // Whether any two shapes share an ID, which shapes added through the inspector's list rather than the outliner do
+    fn has_duplicate_shape_ids(&self) -> bool {
+        let mut ids = HashSet::new();
+        !self.shapes.iter().all(|shape| ids.insert(shape.id))
+    }
+
+    // Give every shape after the first with an ID a new one
+    fn assign_unique_shape_ids(&mut self) {
+        let mut next_id = self.next_shape_id();
+        let mut ids = HashSet::new();
+
+        for shape in self.shapes.iter_mut() {
+            if !ids.insert(shape.id) {
+                shape.id = next_id;
+                next_id.0 += 1;
+            }
+        }
+    }
+
+    // The shapes as they are sent to the shader, hidden by their groups
+    pub fn shader_shapes(&self) -> Vec<Shape> {
+        self.shapes
+            .iter()
+            .enumerate()
+            .map(|(i, shape)| Shape {
+                hidden: self.is_shape_hidden(i) as u32,
+                ..shape.clone().into()
+            })
+            .collect()
+    }
+}
+
+// Convert a vector to a sized array, with empty values when the vec is not big enough for the array
+pub fn vec_to_sized_array<T: Default + Copy, const N: usize>(vec: Vec<T>) -> [T; N] {
+    vec.try_into().unwrap_or_else(|vec: Vec<T>| {
+        vec.into_iter()
+            .enumerate()
+            .take(N)
+            .fold([T::default(); N], |mut acc, (i, elem)| {
+                acc[i] = elem;
+
+                acc
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named_shapes(names: &[&str]) -> ShaderMatInspector {
+        let shapes = names
+            .iter()
+            .enumerate()
+            .map(|(i, &name)| ShapeInspector {
+                name: name.to_owned(),
+                ..ShapeInspector::template(ShapeType::Sphere, ShapeId(i as u32 * 10), Vec3::ZERO)
+            })
+            .collect();
+
+        ShaderMatInspector {
+            shapes,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn lookups_follow_shapes_when_reordered() {
+        let mut inspector_mat = named_shapes(&["floor", "ball", "box"]);
+        assert_eq!(inspector_mat.shape_index_by_id(ShapeId(10)), Some(1));
+        assert_eq!(inspector_mat.shape_index_by_name("box"), Some(2));
+
+        inspector_mat.shapes.swap(0, 2);
+        assert_eq!(inspector_mat.shape_index_by_id(ShapeId(20)), Some(0));
+        assert_eq!(inspector_mat.shape_index_by_name("floor"), Some(2));
+        assert_eq!(inspector_mat.shape_index_by_name("ball"), Some(1));
+
+        assert_eq!(inspector_mat.shape_index_by_id(ShapeId(5)), None);
+        assert_eq!(inspector_mat.shape_index_by_name("portal"), None);
+    }
+
+    #[test]
+    fn duplicate_ids_are_replaced() {
+        let mut inspector_mat = named_shapes(&["a", "b", "c"]);
+        inspector_mat.shapes[2].id = ShapeId(0);
+        assert!(inspector_mat.has_duplicate_shape_ids());
+
+        inspector_mat.assign_unique_shape_ids();
+        assert!(!inspector_mat.has_duplicate_shape_ids());
+        // The first shape with the ID keeps it
+        assert_eq!(inspector_mat.shape_index_by_id(ShapeId(0)), Some(0));
+        assert_eq!(inspector_mat.shapes[2].id, ShapeId(11));
+    }
+}
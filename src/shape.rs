@@ -2,9 +2,19 @@ use bevy::{
     prelude::*,
     render::render_resource::{AsBindGroup, ShaderType},
 };
-use bevy_inspector_egui::InspectorOptions;
+use bevy_inspector_egui::{
+    bevy_egui::egui, inspector_egui_impls::InspectorPrimitive, prelude::ReflectInspectorOptions,
+    reflect_inspector::InspectorUi, InspectorOptions,
+};
+use std::any::Any;
+
+use crate::{
+    materials::{ShapeMaterial, ShapeMaterialInspector},
+    modifiers::{ShapeDisplacement, ShapeDisplacementInspector, ShapeDomain, ShapeDomainInspector},
+    physics::ShapePhysics,
+};
 
-#[derive(Debug, Copy, Clone, Default, Reflect)]
+#[derive(Debug, Copy, Clone, Default, Reflect, PartialEq, Eq)]
 pub enum ShapeType {
     #[default]
     None,
@@ -12,29 +22,301 @@ pub enum ShapeType {
     Cube,
     Plane,
     Portal,
+    Mandelbulb,
+    MengerSponge,
+    SierpinskiTetrahedron,
+    Mandelbox,
+    Terrain,
+}
+
+// Unique among the shapes, and kept when the shape is moved in the list. Shown in the inspector but not editable there,
+// as physics and animation rely on no two shapes sharing one
+#[derive(Debug, Copy, Clone, Default, Reflect, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ShapeId(pub u32);
+
+impl InspectorPrimitive for ShapeId {
+    fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        options: &dyn Any,
+        id: egui::Id,
+        env: InspectorUi<'_, '_>,
+    ) -> bool {
+        self.ui_readonly(ui, options, id, env);
+        false
+    }
+
+    fn ui_readonly(&self, ui: &mut egui::Ui, _: &dyn Any, _: egui::Id, _: InspectorUi<'_, '_>) {
+        ui.label(self.0.to_string());
+    }
 }
 
 #[derive(Debug, AsBindGroup, Clone, Asset, TypePath, ShaderType, Copy)]
 #[repr(C)]
 pub struct Shape {
     pub shape_type: u32,
+    // Non zero if the shape isn't drawn, set from the shape's and its group's visibility
+    pub hidden: u32,
+    // Stays the same when shapes are reordered, so animation follows the shape
+    pub id: u32,
+    // Non zero if the shape is moved by physics, which replaces its animation
+    pub dynamic: u32,
     pub pos: Vec3,
     pub size: Vec3,
+    // A quaternion, rotating the shape about its position
+    pub rotation: Vec4,
+    pub fractal: ShapeFractal,
+    pub domain: ShapeDomain,
+    pub displacement: ShapeDisplacement,
+    pub terrain: ShapeTerrain,
+    pub plane: ShapePlane,
+    pub material: ShapeMaterial,
 }
 
-#[derive(Debug, Copy, Clone, Asset, Reflect, Resource, InspectorOptions, Component, Default)]
+#[derive(Debug, Clone, Asset, Reflect, Resource, InspectorOptions, Component, Default)]
 pub struct ShapeInspector {
+    pub id: ShapeId,
+    pub name: String,
     pub shape_type: ShapeType,
+    pub hidden: bool,
+    // Locked shapes can't be picked or moved with the gizmo
+    pub locked: bool,
+    // The index in ShaderMatInspector::groups
+    pub group: Option<usize>,
     pub pos: Vec3,
     pub size: Vec3,
+    pub rotation: Quat,
+    pub fractal: ShapeFractal,
+    pub domain: ShapeDomainInspector,
+    pub displacement: ShapeDisplacementInspector,
+    pub terrain: ShapeTerrain,
+    pub plane: ShapePlaneInspector,
+    pub material: ShapeMaterialInspector,
+    pub physics: ShapePhysics,
+}
+
+impl ShapeInspector {
+    // A shape of each type with settings that look reasonable straight away, used when adding shapes
+    pub fn template(shape_type: ShapeType, id: ShapeId, pos: Vec3) -> Self {
+        let size = match shape_type {
+            ShapeType::Cube => Vec3::splat(1.),
+            ShapeType::Portal => Vec3::new(1.2, 2., 0.1),
+            ShapeType::Plane | ShapeType::Terrain => Vec3::ZERO,
+            _ => Vec3::new(1., 0., 0.),
+        };
+
+        Self {
+            id,
+            name: format!("{shape_type:?} {}", id.0),
+            shape_type,
+            pos,
+            size,
+            fractal: ShapeFractal::for_shape_type(shape_type),
+            plane: ShapePlaneInspector {
+                thickness: 0.05,
+                half_extents: Vec2::splat(2.),
+                ..default()
+            },
+            ..Shape::default().into()
+        }
+    }
+}
+
+// Parameters for the fractal shape types, ignored by the other shapes
+#[derive(Debug, Copy, Clone, ShaderType, Reflect, InspectorOptions)]
+#[reflect(InspectorOptions)]
+pub struct ShapeFractal {
+    #[inspector(min = 1, max = 64)]
+    pub iterations: u32,
+    // The exponent of a Mandelbulb, or the scale of each iteration for the other fractals
+    pub power: f32,
+    #[inspector(min = 0.)]
+    pub bailout: f32,
+}
+
+impl ShapeFractal {
+    // Parameters which give the classic version of each fractal
+    pub fn for_shape_type(shape_type: ShapeType) -> Self {
+        match shape_type {
+            ShapeType::MengerSponge => Self {
+                iterations: 4,
+                power: 3.,
+                bailout: 0.,
+            },
+            ShapeType::SierpinskiTetrahedron => Self {
+                iterations: 10,
+                power: 2.,
+                bailout: 4.,
+            },
+            ShapeType::Mandelbox => Self {
+                iterations: 12,
+                power: -1.5,
+                bailout: 100.,
+            },
+            _ => Self::default(),
+        }
+    }
+}
+
+// Parameters for the terrain's fBm heightfield, ignored by the other shapes
+#[derive(Debug, Copy, Clone, ShaderType, Reflect, InspectorOptions)]
+#[reflect(InspectorOptions)]
+pub struct ShapeTerrain {
+    #[inspector(min = 1, max = 12)]
+    pub octaves: u32,
+    // How much the frequency increases with each octave
+    #[inspector(min = 1.)]
+    pub lacunarity: f32,
+    // How much the amplitude decreases with each octave
+    #[inspector(min = 0., max = 1.)]
+    pub gain: f32,
+    // The horizontal size of the largest features
+    #[inspector(min = 0.01)]
+    pub scale: f32,
+    // The furthest the ground can be above or below the terrain's position
+    pub height: f32,
+}
+
+#[derive(Debug, Copy, Clone, Default, Reflect, PartialEq, Eq)]
+pub enum PlanePattern {
+    #[default]
+    None,
+    Checkerboard,
+    Grid,
+}
+
+#[derive(Debug, Copy, Clone, ShaderType)]
+pub struct ShapePlane {
+    pub normal: Vec3,
+    pub offset: f32,
+    pub thickness: f32,
+    pub half_extents: Vec2,
+    pub pattern: u32,
+    pub pattern_scale: f32,
+}
+
+// Parameters for the plane, ignored by the other shapes
+#[derive(Debug, Copy, Clone, Reflect, InspectorOptions)]
+#[reflect(InspectorOptions)]
+pub struct ShapePlaneInspector {
+    // The direction the plane faces, normalised before it is sent to the shader
+    pub normal: Vec3,
+    // How far the plane is from the shape's position along its normal
+    pub offset: f32,
+    // Half the thickness of the slab, zero for a flat plane
+    #[inspector(min = 0.)]
+    pub thickness: f32,
+    // Half the width and length of the plane, along the axes perpendicular to the normal (zero is infinite)
+    pub half_extents: Vec2,
+    pub pattern: PlanePattern,
+    // The size of one square of the pattern
+    #[inspector(min = 0.01)]
+    pub pattern_scale: f32,
+}
+
+impl Default for ShapePlane {
+    fn default() -> Self {
+        Self {
+            normal: Vec3::Y,
+            offset: 0.,
+            thickness: 0.,
+            half_extents: Vec2::ZERO,
+            pattern: PlanePattern::None.into(),
+            pattern_scale: 1.,
+        }
+    }
+}
+
+impl Default for ShapePlaneInspector {
+    fn default() -> Self {
+        ShapePlane::default().into()
+    }
+}
+
+impl From<PlanePattern> for u32 {
+    fn from(pattern: PlanePattern) -> Self {
+        match pattern {
+            PlanePattern::None => 0,
+            PlanePattern::Checkerboard => 1,
+            PlanePattern::Grid => 2,
+        }
+    }
+}
+
+impl From<u32> for PlanePattern {
+    fn from(pattern: u32) -> Self {
+        match pattern {
+            1 => Self::Checkerboard,
+            2 => Self::Grid,
+            _ => Self::None,
+        }
+    }
+}
+
+impl From<ShapePlaneInspector> for ShapePlane {
+    fn from(inspector: ShapePlaneInspector) -> Self {
+        Self {
+            normal: inspector.normal.try_normalize().unwrap_or(Vec3::Y),
+            offset: inspector.offset,
+            thickness: inspector.thickness,
+            half_extents: inspector.half_extents.max(Vec2::ZERO),
+            pattern: inspector.pattern.into(),
+            pattern_scale: inspector.pattern_scale,
+        }
+    }
+}
+
+impl From<ShapePlane> for ShapePlaneInspector {
+    fn from(plane: ShapePlane) -> Self {
+        Self {
+            normal: plane.normal,
+            offset: plane.offset,
+            thickness: plane.thickness,
+            half_extents: plane.half_extents,
+            pattern: plane.pattern.into(),
+            pattern_scale: plane.pattern_scale,
+        }
+    }
+}
+
+impl Default for ShapeTerrain {
+    fn default() -> Self {
+        Self {
+            octaves: 5,
+            lacunarity: 2.,
+            gain: 0.5,
+            scale: 8.,
+            height: 3.,
+        }
+    }
+}
+
+impl Default for ShapeFractal {
+    fn default() -> Self {
+        Self {
+            iterations: 8,
+            power: 8.,
+            bailout: 2.,
+        }
+    }
 }
 
 impl Default for Shape {
     fn default() -> Self {
         Self {
             shape_type: u32::default(),
+            hidden: 0,
+            id: 0,
+            dynamic: 0,
             pos: Vec3::default(),
             size: Vec3::splat(1.),
+            rotation: Quat::IDENTITY.into(),
+            fractal: ShapeFractal::default(),
+            domain: ShapeDomain::default(),
+            displacement: ShapeDisplacement::default(),
+            terrain: ShapeTerrain::default(),
+            plane: ShapePlane::default(),
+            material: ShapeMaterial::default(),
         }
     }
 }
@@ -47,6 +329,11 @@ impl From<ShapeType> for u32 {
             ShapeType::Cube => 2,
             ShapeType::Plane => 3,
             ShapeType::Portal => 4,
+            ShapeType::Mandelbulb => 5,
+            ShapeType::MengerSponge => 6,
+            ShapeType::SierpinskiTetrahedron => 7,
+            ShapeType::Mandelbox => 8,
+            ShapeType::Terrain => 9,
         }
     }
 }
@@ -58,6 +345,11 @@ impl From<u32> for ShapeType {
             2 => Self::Cube,
             3 => Self::Plane,
             4 => Self::Portal,
+            5 => Self::Mandelbulb,
+            6 => Self::MengerSponge,
+            7 => Self::SierpinskiTetrahedron,
+            8 => Self::Mandelbox,
+            9 => Self::Terrain,
             _ => Self::None,
         }
     }
@@ -67,8 +359,18 @@ impl From<ShapeInspector> for Shape {
     fn from(inspector: ShapeInspector) -> Self {
         Self {
             shape_type: inspector.shape_type.into(),
+            hidden: inspector.hidden as u32,
+            id: inspector.id.0,
+            dynamic: inspector.physics.dynamic as u32,
             pos: inspector.pos,
             size: inspector.size,
+            rotation: inspector.rotation.normalize().into(),
+            fractal: inspector.fractal,
+            domain: inspector.domain.into(),
+            displacement: inspector.displacement.into(),
+            terrain: inspector.terrain,
+            plane: inspector.plane.into(),
+            material: inspector.material.into(),
         }
     }
 }
@@ -76,9 +378,26 @@ impl From<ShapeInspector> for Shape {
 impl From<Shape> for ShapeInspector {
     fn from(shape: Shape) -> Self {
         Self {
+            id: ShapeId(shape.id),
+            // Names only exist on the CPU, so are made from the type and ID
+            name: format!("{:?} {}", ShapeType::from(shape.shape_type), shape.id),
             shape_type: shape.shape_type.into(),
+            hidden: shape.hidden != 0,
+            locked: false,
+            group: None,
             pos: shape.pos,
             size: shape.size,
+            rotation: Quat::from_vec4(shape.rotation),
+            fractal: shape.fractal,
+            domain: shape.domain.into(),
+            displacement: shape.displacement.into(),
+            terrain: shape.terrain,
+            plane: shape.plane.into(),
+            material: shape.material.into(),
+            physics: ShapePhysics {
+                dynamic: shape.dynamic != 0,
+                ..default()
+            },
         }
     }
 }
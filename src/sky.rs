@@ -0,0 +1,147 @@
+use bevy::{prelude::*, render::render_resource::ShaderType};
+use bevy_inspector_egui::{prelude::ReflectInspectorOptions, InspectorOptions};
+
+#[derive(Debug, Copy, Clone, Default, Reflect, PartialEq, Eq)]
+pub enum SkyType {
+    // Blends from the horizon colour to the zenith colour
+    Gradient,
+    // Rayleigh and Mie scattering of the sun's light, so the sky changes colour as the sun moves
+    #[default]
+    Atmosphere,
+}
+
+// The background of the scene, which also lights physically based materials and positions directional lights
+#[derive(Debug, Copy, Clone, ShaderType)]
+pub struct ShaderSky {
+    pub sky_type: u32,
+    // Points towards the sun
+    pub sun_direction: Vec3,
+    pub sun_colour: Vec3,
+    pub sun_intensity: f32,
+    pub sun_disc: u32,
+    // The angular radius of the sun disc, in radians
+    pub sun_size: f32,
+    pub stars: u32,
+    pub star_density: f32,
+    pub zenith_colour: Vec3,
+    pub horizon_colour: Vec3,
+    pub ground_colour: Vec3,
+}
+
+#[derive(Debug, Copy, Clone, Reflect, InspectorOptions)]
+#[reflect(InspectorOptions)]
+pub struct ShaderSkyInspector {
+    pub sky_type: SkyType,
+    // The sun's angle above the horizon and around from +z, in degrees
+    #[inspector(min = -90., max = 90.)]
+    pub sun_elevation: f32,
+    pub sun_azimuth: f32,
+    pub sun_colour: Vec3,
+    #[inspector(min = 0.)]
+    pub sun_intensity: f32,
+    pub sun_disc: bool,
+    // In degrees
+    #[inspector(min = 0.01, max = 20.)]
+    pub sun_size: f32,
+    // Stars come out when the sun is below the horizon
+    pub stars: bool,
+    #[inspector(min = 0., max = 1.)]
+    pub star_density: f32,
+    // Only used by the gradient sky
+    pub zenith_colour: Vec3,
+    pub horizon_colour: Vec3,
+    // The colour below the horizon, which the atmosphere tints with the light of the sky
+    pub ground_colour: Vec3,
+}
+
+impl Default for ShaderSky {
+    fn default() -> Self {
+        Self {
+            sky_type: SkyType::default().into(),
+            sun_direction: sun_direction(35., 30.),
+            sun_colour: Vec3::ONE,
+            sun_intensity: 20.,
+            sun_disc: true.into(),
+            sun_size: 1_f32.to_radians(),
+            stars: true.into(),
+            star_density: 0.5,
+            zenith_colour: Vec3::new(0.1, 0.2, 0.7),
+            horizon_colour: Vec3::new(0.3, 0.2, 0.5),
+            ground_colour: Vec3::new(0.2, 0.18, 0.15),
+        }
+    }
+}
+
+impl Default for ShaderSkyInspector {
+    fn default() -> Self {
+        ShaderSky::default().into()
+    }
+}
+
+impl From<SkyType> for u32 {
+    fn from(sky_type: SkyType) -> Self {
+        match sky_type {
+            SkyType::Gradient => 0,
+            SkyType::Atmosphere => 1,
+        }
+    }
+}
+
+impl From<u32> for SkyType {
+    fn from(sky_type: u32) -> Self {
+        match sky_type {
+            1 => Self::Atmosphere,
+            _ => Self::Gradient,
+        }
+    }
+}
+
+impl From<ShaderSkyInspector> for ShaderSky {
+    fn from(inspector: ShaderSkyInspector) -> Self {
+        Self {
+            sky_type: inspector.sky_type.into(),
+            sun_direction: sun_direction(inspector.sun_elevation, inspector.sun_azimuth),
+            sun_colour: inspector.sun_colour,
+            sun_intensity: inspector.sun_intensity,
+            sun_disc: inspector.sun_disc.into(),
+            sun_size: inspector.sun_size.to_radians(),
+            stars: inspector.stars.into(),
+            star_density: inspector.star_density,
+            zenith_colour: inspector.zenith_colour,
+            horizon_colour: inspector.horizon_colour,
+            ground_colour: inspector.ground_colour,
+        }
+    }
+}
+
+impl From<ShaderSky> for ShaderSkyInspector {
+    fn from(sky: ShaderSky) -> Self {
+        let sun_direction = sky.sun_direction.normalize_or(Vec3::Y);
+
+        Self {
+            sky_type: sky.sky_type.into(),
+            sun_elevation: sun_direction.y.asin().to_degrees(),
+            sun_azimuth: sun_direction.x.atan2(sun_direction.z).to_degrees(),
+            sun_colour: sky.sun_colour,
+            sun_intensity: sky.sun_intensity,
+            sun_disc: sky.sun_disc != 0,
+            sun_size: sky.sun_size.to_degrees(),
+            stars: sky.stars != 0,
+            star_density: sky.star_density,
+            zenith_colour: sky.zenith_colour,
+            horizon_colour: sky.horizon_colour,
+            ground_colour: sky.ground_colour,
+        }
+    }
+}
+
+// The direction towards the sun, from angles in degrees
+pub fn sun_direction(elevation: f32, azimuth: f32) -> Vec3 {
+    let (elevation, azimuth) = (elevation.to_radians(), azimuth.to_radians());
+
+    Vec3::new(
+        elevation.cos() * azimuth.sin(),
+        elevation.sin(),
+        elevation.cos() * azimuth.cos(),
+    )
+}
@@ -0,0 +1,98 @@
+use bevy::{prelude::*, render::render_resource::ShaderType};
+use bevy_inspector_egui::{prelude::ReflectInspectorOptions, InspectorOptions};
+
+// How far apart the eyes are, and how far away things line up in both eyes, in world units
+pub const STEREO_DEFAULT_EYE_SEPARATION: f32 = 0.064;
+pub const STEREO_DEFAULT_CONVERGENCE: f32 = 5.;
+
+// How the left and right eye views are put on the screen, see stereo.wgsl
+#[derive(Debug, Copy, Clone, Default, Reflect, PartialEq, Eq)]
+pub enum StereoMode {
+    #[default]
+    None,
+    // The left eye on the left half of the screen and the right eye on the right, for headsets and parallel viewing
+    SideBySide,
+    // The left eye in red and the right eye in cyan, for red-cyan glasses
+    Anaglyph,
+}
+
+impl From<StereoMode> for u32 {
+    fn from(mode: StereoMode) -> Self {
+        match mode {
+            StereoMode::None => 0,
+            StereoMode::SideBySide => 1,
+            StereoMode::Anaglyph => 2,
+        }
+    }
+}
+
+impl From<u32> for StereoMode {
+    fn from(mode: u32) -> Self {
+        match mode {
+            1 => Self::SideBySide,
+            2 => Self::Anaglyph,
+            _ => Self::None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, ShaderType)]
+pub struct ShaderStereo {
+    pub mode: u32,
+    pub eye_separation: f32,
+    pub convergence: f32,
+    pub swap_eyes: u32,
+}
+
+#[derive(Debug, Copy, Clone, Reflect, InspectorOptions)]
+#[reflect(InspectorOptions)]
+pub struct ShaderStereoInspector {
+    pub mode: StereoMode,
+    // The interpupillary distance, the eyes are moved half of it either side of the camera along its right
+    #[inspector(min = 0.)]
+    pub eye_separation: f32,
+    // The distance things appear at the depth of the screen, closer things pop out and further ones sink in
+    #[inspector(min = 0.1)]
+    pub convergence: f32,
+    // Puts the right eye on the left, for cross-eyed viewing or cyan-red glasses
+    pub swap_eyes: bool,
+}
+
+impl Default for ShaderStereo {
+    fn default() -> Self {
+        Self {
+            mode: StereoMode::None.into(),
+            eye_separation: STEREO_DEFAULT_EYE_SEPARATION,
+            convergence: STEREO_DEFAULT_CONVERGENCE,
+            swap_eyes: false.into(),
+        }
+    }
+}
+
+impl Default for ShaderStereoInspector {
+    fn default() -> Self {
+        ShaderStereo::default().into()
+    }
+}
+
+impl From<ShaderStereoInspector> for ShaderStereo {
+    fn from(inspector: ShaderStereoInspector) -> Self {
+        Self {
+            mode: inspector.mode.into(),
+            eye_separation: inspector.eye_separation,
+            convergence: inspector.convergence,
+            swap_eyes: inspector.swap_eyes.into(),
+        }
+    }
+}
+
+impl From<ShaderStereo> for ShaderStereoInspector {
+    fn from(stereo: ShaderStereo) -> Self {
+        Self {
+            mode: stereo.mode.into(),
+            eye_separation: stereo.eye_separation,
+            convergence: stereo.convergence,
+            swap_eyes: stereo.swap_eyes != 0,
+        }
+    }
+}
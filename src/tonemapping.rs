@@ -0,0 +1,41 @@
+use bevy::prelude::*;
+
+// Stops of exposure the inspector allows either way
+pub const EXPOSURE_RANGE: f32 = 10.;
+
+// How the HDR colour from the lighting is mapped into the display's range, see tonemapping.wgsl
+#[derive(Debug, Copy, Clone, Default, Reflect, PartialEq, Eq)]
+pub enum Tonemapper {
+    // Clip anything brighter than white
+    None,
+    Reinhard,
+    #[default]
+    Aces,
+    AgX,
+    // Hable's Uncharted 2 curve
+    Filmic,
+}
+
+impl From<Tonemapper> for u32 {
+    fn from(tonemapper: Tonemapper) -> Self {
+        match tonemapper {
+            Tonemapper::None => 0,
+            Tonemapper::Reinhard => 1,
+            Tonemapper::Aces => 2,
+            Tonemapper::AgX => 3,
+            Tonemapper::Filmic => 4,
+        }
+    }
+}
+
+impl From<u32> for Tonemapper {
+    fn from(tonemapper: u32) -> Self {
+        match tonemapper {
+            1 => Self::Reinhard,
+            2 => Self::Aces,
+            3 => Self::AgX,
+            4 => Self::Filmic,
+            _ => Self::None,
+        }
+    }
+}